@@ -0,0 +1,19 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rusty_diff_match_patch::{Dmp, LengthUnit};
+
+// Input is the source text and the delta, separated by the first NUL.
+fuzz_target!(|data: &str| {
+    let (text1, delta) = data.split_once('\0').unwrap_or(("", data));
+    let dmp = Dmp::default();
+    for unit in [
+        LengthUnit::UnicodeScalar,
+        LengthUnit::UTF16,
+        LengthUnit::UTF8,
+    ] {
+        if let Ok(mut diffs) = dmp.diff_parse_delta(text1, delta, unit) {
+            assert_eq!(text1, dmp.diff_text1(&mut diffs));
+        }
+    }
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rusty_diff_match_patch::Dmp;
+
+fuzz_target!(|text: &str| {
+    let _ = Dmp::default().patch1_parse(text);
+});
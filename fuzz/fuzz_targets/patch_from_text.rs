@@ -0,0 +1,19 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rusty_diff_match_patch::{Dmp, LengthUnit};
+
+fuzz_target!(|text: &str| {
+    let dmp = Dmp::default();
+    for unit in [
+        LengthUnit::UnicodeScalar,
+        LengthUnit::UTF16,
+        LengthUnit::UTF8,
+    ] {
+        if let Ok(mut patches) = dmp.patch_parse_unit(text, unit) {
+            // Whatever parses has to survive a round trip.
+            let text = dmp.patch_to_text(&mut patches);
+            assert_eq!(Ok(patches), dmp.patch_parse_unit(&text, unit));
+        }
+    }
+});
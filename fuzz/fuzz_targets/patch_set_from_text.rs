@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rusty_diff_match_patch::Dmp;
+
+fuzz_target!(|text: &str| {
+    let dmp = Dmp::default();
+    if let Ok(patch_set) = dmp.patch_set_from_text(text) {
+        let text = dmp.patch_set_to_text(&patch_set);
+        assert_eq!(Ok(patch_set), dmp.patch_set_from_text(&text));
+    }
+});
@@ -0,0 +1,159 @@
+#ifndef RUSTY_DIFF_MATCH_PATCH_H
+#define RUSTY_DIFF_MATCH_PATCH_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit. */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * Outcome of a call.
+ */
+typedef enum DmpStatus {
+  DMP_STATUS_OK = 0,
+  DMP_STATUS_INVALID_ARGUMENT = 1,
+  DMP_STATUS_PARSE_ERROR = 2,
+  DMP_STATUS_PANIC = 3,
+} DmpStatus;
+
+typedef struct Dmp Dmp;
+
+/**
+ * Text owned by the library, NUL terminated; len excludes the NUL.
+ */
+typedef struct DmpString {
+  char *ptr;
+  size_t len;
+} DmpString;
+
+/**
+ * One diff: -1 for a deletion, 0 for an equality, 1 for an insertion.
+ */
+typedef struct DmpDiff {
+  int32_t op;
+  struct DmpString text;
+} DmpDiff;
+
+typedef struct DmpDiffs {
+  struct DmpDiff *ptr;
+  size_t len;
+} DmpDiffs;
+
+/**
+ * The patched text and whether each patch applied.
+ */
+typedef struct DmpApplyResult {
+  struct DmpString text;
+  bool *results;
+  size_t len;
+} DmpApplyResult;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Create a Dmp with the default settings.  Release it with dmp_free.
+ */
+struct Dmp *dmp_new(void);
+
+/**
+ * Release a Dmp.
+ *
+ * # Safety
+ *
+ * dmp must be NULL or come from dmp_new, and must not be used afterwards.
+ */
+void dmp_free(struct Dmp *dmp);
+
+/**
+ * Set how many seconds to map a diff before giving up, 0 for no limit.
+ *
+ * # Safety
+ *
+ * dmp must come from dmp_new.
+ */
+void dmp_set_diff_timeout(struct Dmp *dmp, double seconds);
+
+/**
+ * Find the differences between two texts.  Release the diffs with
+ * dmp_diffs_free.
+ *
+ * # Safety
+ *
+ * dmp must come from dmp_new, the texts must point to len readable bytes
+ * and out must point to writable memory for one DmpDiffs.
+ */
+enum DmpStatus dmp_diff_main(const struct Dmp *dmp,
+                             const char *text1,
+                             size_t text1_len,
+                             const char *text2,
+                             size_t text2_len,
+                             bool checklines,
+                             struct DmpDiffs *out);
+
+/**
+ * Release diffs from dmp_diff_main.
+ *
+ * # Safety
+ *
+ * diffs must come from dmp_diff_main and must not be used afterwards.
+ */
+void dmp_diffs_free(struct DmpDiffs diffs);
+
+/**
+ * Compute the patches turning text1 into text2, as patch text.  Release
+ * the text with dmp_string_free.
+ *
+ * # Safety
+ *
+ * dmp must come from dmp_new, the texts must point to len readable bytes
+ * and out must point to writable memory for one DmpString.
+ */
+enum DmpStatus dmp_patch_make(const struct Dmp *dmp,
+                              const char *text1,
+                              size_t text1_len,
+                              const char *text2,
+                              size_t text2_len,
+                              struct DmpString *out);
+
+/**
+ * Merge patch text onto a text.  Release the result with
+ * dmp_apply_result_free.
+ *
+ * # Safety
+ *
+ * dmp must come from dmp_new, the texts must point to len readable bytes
+ * and out must point to writable memory for one DmpApplyResult.
+ */
+enum DmpStatus dmp_patch_apply(const struct Dmp *dmp,
+                               const char *patch_text,
+                               size_t patch_text_len,
+                               const char *text,
+                               size_t text_len,
+                               struct DmpApplyResult *out);
+
+/**
+ * Release a result from dmp_patch_apply.
+ *
+ * # Safety
+ *
+ * result must come from dmp_patch_apply and must not be used afterwards.
+ */
+void dmp_apply_result_free(struct DmpApplyResult result);
+
+/**
+ * Release text returned by the library.
+ *
+ * # Safety
+ *
+ * text must come from this library and must not be used afterwards.
+ */
+void dmp_string_free(struct DmpString text);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* RUSTY_DIFF_MATCH_PATCH_H */
@@ -0,0 +1,49 @@
+/*Diffs for async code.
+
+diff_main can take as long as diff_timeout, and stalls every other task on
+the executor thread while it runs.  diff_main_async runs it on the blocking
+thread pool of tokio instead.  Dropping the future, say because a request
+was aborted or lost a race against a timer, makes the diff give up at its
+next step rather than run on for nobody.
+*/
+
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use super::dmp::{Diff, Dmp};
+
+/// Raises the cancel flag of a diff when the future awaiting it goes away.
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Dmp {
+    /// diff_main on the blocking thread pool of the current tokio runtime.
+    /// The diff is cancelled when the future is dropped.
+    ///
+    /// Args:
+    ///     text1: Old text to be diffed.
+    ///     text2: New text to be diffed.
+    ///     checklines: Speedup flag, as for diff_main.
+    /// Returns:
+    ///     Vector of diffs as changes, the same as diff_main.
+    pub async fn diff_main_async(&self, text1: &str, text2: &str, checklines: bool) -> Vec<Diff> {
+        let dmp = self.clone();
+        let (text1, text2) = (text1.to_string(), text2.to_string());
+        let cancel = Arc::new(AtomicBool::new(false));
+        let _guard = CancelOnDrop(cancel.clone());
+        let task = tokio::task::spawn_blocking(move || {
+            dmp.diff_main_cancellable(&text1, &text2, checklines, &cancel)
+        });
+        match task.await {
+            Ok(diffs) => diffs,
+            Err(err) if err.is_panic() => panic::resume_unwind(err.into_panic()),
+            Err(err) => panic!("{err}"),
+        }
+    }
+}
@@ -0,0 +1,66 @@
+/*Line attribution across revisions.
+
+blame walks a sequence of revisions and tells, for every line of the last
+one, which revision introduced it.  Each line carries the revision it came
+from; a line diff between two revisions carries the origins of kept lines
+over, gives added lines the new revision and drops deleted ones.
+*/
+
+use super::dmp::{Diff, Dmp};
+
+/// A line of the last revision and where it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlameLine {
+    pub line: String,
+    // Index of the revision which introduced the line.
+    pub revision: usize,
+    pub author: String,
+}
+
+fn line_count(text: &str) -> usize {
+    text.split_inclusive('\n').count()
+}
+
+impl Dmp {
+    /// Attribute every line of the last revision to the revision that
+    /// introduced it.  Lines are compared with the ignore_space,
+    /// ignore_case and normalization options, so a line changed only in
+    /// ways those ignore keeps its origin.  ignore_blank_lines does not
+    /// apply, every line needs an origin.
+    ///
+    /// Args:
+    ///     revisions: (author, text) of each revision, oldest first.
+    ///
+    /// Returns:
+    ///     Vector with one entry per line of the last revision.
+    pub fn blame(&self, revisions: &[(&str, &str)]) -> Vec<BlameLine> {
+        let Some((_, first)) = revisions.first() else {
+            return vec![];
+        };
+        let mut origins: Vec<usize> = vec![0; line_count(first)];
+        for (revision, pair) in revisions.windows(2).enumerate() {
+            let revision = revision + 1;
+            let mut old = origins.into_iter();
+            origins = vec![];
+            for diff in self.diff_line_keys(pair[0].1, pair[1].1) {
+                let count = diff.text().chars().count();
+                match diff {
+                    Diff::Keep(_) => origins.extend(old.by_ref().take(count)),
+                    Diff::Delete(_) => {
+                        old.by_ref().take(count).for_each(drop);
+                    }
+                    Diff::Add(_) => origins.extend(std::iter::repeat_n(revision, count)),
+                }
+            }
+        }
+        let (_, last) = revisions[revisions.len() - 1];
+        last.split_inclusive('\n')
+            .zip(origins)
+            .map(|(line, revision)| BlameLine {
+                line: line.to_string(),
+                revision,
+                author: revisions[revision].0.to_string(),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,218 @@
+/*Validated construction of Dmp.
+
+Dmp is a struct of public fields, some of which have special values:
+match_maxbits == 0 disables patch splitting, diff_timeout == None never
+gives up and thresholds only make sense between 0.0 and 1.0.  DmpBuilder
+sets the same fields but checks them before handing out a Dmp, and the
+presets cover the common trade-offs.
+*/
+
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::time::Duration;
+
+use super::dmp::Dmp;
+use super::normalize::{IgnoreSpace, Normalization};
+use super::scoring::{BoundaryScorer, BoundaryScoring};
+
+/// Why a configuration was rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    // A threshold is NaN or outside 0.0 to 1.0.
+    InvalidThreshold {
+        name: &'static str,
+        value: f32,
+    },
+    // The patch context leaves no room for the pattern in match_maxbits.
+    PatchMarginTooLarge {
+        patch_margin: usize,
+        match_maxbits: usize,
+    },
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::InvalidThreshold { name, value } => {
+                write!(f, "{name} must be between 0.0 and 1.0, got {value}")
+            }
+            ConfigError::PatchMarginTooLarge {
+                patch_margin,
+                match_maxbits,
+            } => write!(
+                f,
+                "patch_margin {patch_margin} leaves no room for a pattern in match_maxbits {match_maxbits}"
+            ),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+/// Builder for a validated Dmp, starting from the defaults or a preset.
+#[derive(Default)]
+pub struct DmpBuilder {
+    dmp: Dmp,
+}
+
+impl From<Dmp> for DmpBuilder {
+    fn from(dmp: Dmp) -> Self {
+        DmpBuilder { dmp }
+    }
+}
+
+impl DmpBuilder {
+    pub fn new() -> DmpBuilder {
+        DmpBuilder::default()
+    }
+
+    /// How long to map a diff before giving up, None to never give up.
+    pub fn diff_timeout(mut self, diff_timeout: Option<Duration>) -> Self {
+        self.dmp.diff_timeout = diff_timeout;
+        self
+    }
+
+    pub fn edit_cost(mut self, edit_cost: usize) -> Self {
+        self.dmp.edit_cost = edit_cost;
+        self
+    }
+
+    pub fn match_distance(mut self, match_distance: usize) -> Self {
+        self.dmp.match_distance = match_distance;
+        self
+    }
+
+    pub fn match_threshold(mut self, match_threshold: f32) -> Self {
+        self.dmp.match_threshold = match_threshold;
+        self
+    }
+
+    /// Longest pattern matched at once, None to never split patches.
+    pub fn match_maxbits(mut self, match_maxbits: Option<usize>) -> Self {
+        self.dmp.match_maxbits = match_maxbits.unwrap_or(0);
+        self
+    }
+
+    pub fn patch_margin(mut self, patch_margin: usize) -> Self {
+        self.dmp.patch_margin = patch_margin;
+        self
+    }
+
+    pub fn patch_delete_threshold(mut self, patch_delete_threshold: f32) -> Self {
+        self.dmp.patch_delete_threshold = patch_delete_threshold;
+        self
+    }
+
+    pub fn ignore_case(mut self, ignore_case: bool) -> Self {
+        self.dmp.ignore_case = ignore_case;
+        self
+    }
+
+    pub fn normalization(mut self, normalization: Normalization) -> Self {
+        self.dmp.normalization = normalization;
+        self
+    }
+
+    pub fn ignore_whitespace(mut self, ignore_whitespace: bool) -> Self {
+        self.dmp.ignore_whitespace = ignore_whitespace;
+        self
+    }
+
+    pub fn ignore_space(mut self, ignore_space: IgnoreSpace) -> Self {
+        self.dmp.ignore_space = ignore_space;
+        self
+    }
+
+    pub fn ignore_blank_lines(mut self, ignore_blank_lines: bool) -> Self {
+        self.dmp.ignore_blank_lines = ignore_blank_lines;
+        self
+    }
+
+    pub fn rename_threshold(mut self, rename_threshold: f32) -> Self {
+        self.dmp.rename_threshold = rename_threshold;
+        self
+    }
+
+    pub fn boundary_scoring(mut self, boundary_scoring: BoundaryScoring) -> Self {
+        self.dmp.boundary_scoring = boundary_scoring;
+        self
+    }
+
+    pub fn boundary_scorer(mut self, scorer: impl BoundaryScorer + 'static) -> Self {
+        self.dmp.boundary_scoring = BoundaryScoring::custom(scorer);
+        self
+    }
+
+    /// Check the configuration and return the Dmp.
+    ///
+    /// Returns:
+    ///     Dmp object, or the first problem found.
+    pub fn build(self) -> Result<Dmp, ConfigError> {
+        self.dmp.validate()?;
+        Ok(self.dmp)
+    }
+}
+
+impl Dmp {
+    /// Start building a Dmp from the defaults.
+    pub fn builder() -> DmpBuilder {
+        DmpBuilder::new()
+    }
+
+    /// Preset for interactive use on large texts: gives up mapping a diff
+    /// after 100ms and folds short equalities into the edits around them.
+    pub fn fast() -> Dmp {
+        Dmp {
+            diff_timeout: Some(Duration::from_millis(100)),
+            edit_cost: 4,
+            ..Dmp::default()
+        }
+    }
+
+    /// Preset for the smallest diffs and careful patching: never gives up
+    /// mapping a diff and only applies patches whose text matches closely.
+    pub fn precise() -> Dmp {
+        Dmp {
+            diff_timeout: None,
+            match_threshold: 0.3,
+            patch_delete_threshold: 0.3,
+            ..Dmp::default()
+        }
+    }
+
+    /// Preset for source code: aligns edits on lines, brackets and
+    /// identifiers and keeps more context around patches, since short
+    /// tokens repeat a lot in code.
+    pub fn code() -> Dmp {
+        Dmp {
+            diff_timeout: Some(Duration::from_secs(1)),
+            patch_margin: 8,
+            boundary_scoring: BoundaryScoring::Code,
+            ..Dmp::default()
+        }
+    }
+
+    /// Check that the fields hold values the algorithms can work with.
+    ///
+    /// Returns:
+    ///     Nothing, or the first problem found.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for (name, value) in [
+            ("match_threshold", self.match_threshold),
+            ("patch_delete_threshold", self.patch_delete_threshold),
+            ("rename_threshold", self.rename_threshold),
+        ] {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(ConfigError::InvalidThreshold { name, value });
+            }
+        }
+        // Patches carry patch_margin chars of context on either side.
+        if self.match_maxbits != 0 && self.patch_margin * 2 >= self.match_maxbits {
+            return Err(ConfigError::PatchMarginTooLarge {
+                patch_margin: self.patch_margin,
+                match_maxbits: self.match_maxbits,
+            });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,356 @@
+/*Diff and patch of whole directory trees.
+
+diff_dirs walks two trees and describes every added, removed, renamed or
+modified text file as one FilePatch of a PatchSet.  Files which cannot be
+read as UTF-8 text are reported next to it instead.  apply_dirs replays such
+a set onto another tree, file by file, without following symbolic links.  Paths inside the set are relative to
+the root and use '/' as separator.
+*/
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use super::dmp::Dmp;
+use super::patch_set::{FilePatch, PatchSet, PatchSetError};
+
+/// Why a file of a patch set could not be applied.
+#[derive(Debug)]
+pub enum ApplyDirError {
+    Io(io::Error),
+    PatchSet(PatchSetError),
+    // The path is absolute, empty, leaves the root or passes a symbolic link.
+    InvalidPath(String),
+}
+
+impl Display for ApplyDirError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApplyDirError::Io(err) => write!(f, "{err}"),
+            ApplyDirError::PatchSet(err) => write!(f, "{err}"),
+            ApplyDirError::InvalidPath(path) => write!(f, "invalid path: {path:?}"),
+        }
+    }
+}
+
+impl Error for ApplyDirError {}
+
+impl From<io::Error> for ApplyDirError {
+    fn from(err: io::Error) -> Self {
+        ApplyDirError::Io(err)
+    }
+}
+
+impl From<PatchSetError> for ApplyDirError {
+    fn from(err: PatchSetError) -> Self {
+        ApplyDirError::PatchSet(err)
+    }
+}
+
+/// Outcome of applying one file of a patch set.
+#[derive(Debug)]
+pub struct FileResult {
+    // New path of the file, or the old one for a removed file.
+    pub path: String,
+    // Whether each patch applied, or why the file was left alone.
+    pub result: Result<Vec<bool>, ApplyDirError>,
+}
+
+/// Outcome of comparing two directory trees.
+#[derive(Debug)]
+pub struct DirsDiff {
+    pub patch_set: PatchSet,
+    // Files of either tree left out of the comparison, e.g. as they are
+    // not UTF-8 text or could not be read.
+    pub skipped: Vec<SkippedFile>,
+}
+
+/// A file diff_dirs could not compare.
+#[derive(Debug)]
+pub struct SkippedFile {
+    pub path: PathBuf,
+    pub error: io::Error,
+}
+
+/// A text file found while walking a tree.
+struct TreeFile {
+    text: String,
+    mode: Option<u32>,
+}
+
+/// Collect all text files below dir, keyed by their path relative to root.
+/// Files which cannot be read as UTF-8 text go to skipped instead.
+fn walk_dir(
+    root: &Path,
+    dir: &Path,
+    files: &mut BTreeMap<String, TreeFile>,
+    skipped: &mut BTreeMap<String, SkippedFile>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            walk_dir(root, &path, files, skipped)?;
+        } else if file_type.is_file() {
+            let relative = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let file = fs::read_to_string(&path).and_then(|text| {
+                let mode = file_mode(&entry.metadata()?);
+                Ok(TreeFile { text, mode })
+            });
+            match file {
+                Ok(file) => {
+                    files.insert(relative, file);
+                }
+                Err(error) => {
+                    skipped.insert(relative, SkippedFile { path, error });
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    // Regular file plus permission bits, as git records it.
+    Some(0o100000 | (metadata.permissions().mode() & 0o777))
+}
+
+#[cfg(not(unix))]
+fn file_mode(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+fn set_file_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o777))
+}
+
+#[cfg(not(unix))]
+fn set_file_mode(_path: &Path, _mode: u32) -> io::Result<()> {
+    Ok(())
+}
+
+/// Resolve a path of a patch set below root.  Each part must be a plain
+/// name, so absolute paths, empty parts and ".." cannot reach outside it.
+/// Neither may any part be a symbolic link, which could point anywhere.
+fn join_path(root: &Path, relative: &str) -> Result<PathBuf, ApplyDirError> {
+    let mut path = root.to_path_buf();
+    for part in relative.split('/') {
+        let mut components = Path::new(part).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(name)), None) if name == part => path.push(part),
+            _ => return Err(ApplyDirError::InvalidPath(relative.to_string())),
+        }
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                return Err(ApplyDirError::InvalidPath(relative.to_string()));
+            }
+            Ok(_) => {}
+            // Parts which do not exist yet are created as directories.
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(path)
+}
+
+impl Dmp {
+    /// Compare two directory trees.  Files only in the old tree are removed,
+    /// files only in the new tree are added, unless a removed and an added
+    /// file are at least rename_threshold similar and make a rename.  Files
+    /// in both trees whose text or mode differ are modified.  A path which
+    /// is not UTF-8 text or cannot be read in either tree is skipped in
+    /// both.
+    ///
+    /// Args:
+    ///     old_root: Root of the old tree.
+    ///     new_root: Root of the new tree.
+    ///
+    /// Returns:
+    ///     DirsDiff with one FilePatch per changed file and the skipped files.
+    pub fn diff_dirs(&self, old_root: &Path, new_root: &Path) -> io::Result<DirsDiff> {
+        let mut old_files = BTreeMap::new();
+        let mut old_skipped = BTreeMap::new();
+        walk_dir(old_root, old_root, &mut old_files, &mut old_skipped)?;
+        let mut new_files = BTreeMap::new();
+        let mut new_skipped = BTreeMap::new();
+        walk_dir(new_root, new_root, &mut new_files, &mut new_skipped)?;
+        for path in old_skipped.keys().chain(new_skipped.keys()) {
+            old_files.remove(path);
+            new_files.remove(path);
+        }
+
+        let mut patch_set = PatchSet::default();
+        let mut removed: Vec<&String> = vec![];
+        for (path, old) in &old_files {
+            match new_files.get(path) {
+                Some(new) => {
+                    if old.text != new.text || old.mode != new.mode {
+                        patch_set
+                            .files
+                            .push(self.diff_dirs_file(Some((path, old)), Some((path, new))));
+                    }
+                }
+                None => removed.push(path),
+            }
+        }
+        let added: Vec<&String> = new_files
+            .keys()
+            .filter(|path| !old_files.contains_key(*path))
+            .collect();
+
+        // Pair removed with added files to find renames.
+        let removed_texts: Vec<&str> = removed
+            .iter()
+            .map(|path| old_files[*path].text.as_str())
+            .collect();
+        let added_texts: Vec<&str> = added
+            .iter()
+            .map(|path| new_files[*path].text.as_str())
+            .collect();
+        let pairs =
+            self.similarity_pairs(&removed_texts, &added_texts, self.rename_threshold, false);
+        let mut renamed: Vec<Option<&String>> = vec![None; removed.len()];
+        let mut added_used = vec![false; added.len()];
+        for pair in pairs {
+            renamed[pair.deleted] = Some(added[pair.added]);
+            added_used[pair.added] = true;
+        }
+
+        for (path, renamed) in removed.iter().zip(renamed) {
+            let new = renamed.map(|new_path| (new_path, &new_files[new_path]));
+            patch_set
+                .files
+                .push(self.diff_dirs_file(Some((path, &old_files[*path])), new));
+        }
+        let added = added
+            .iter()
+            .zip(added_used)
+            .filter(|(_, used)| !used)
+            .map(|(path, _)| *path);
+        for path in added {
+            patch_set
+                .files
+                .push(self.diff_dirs_file(None, Some((path, &new_files[path]))));
+        }
+        Ok(DirsDiff {
+            patch_set,
+            skipped: old_skipped
+                .into_values()
+                .chain(new_skipped.into_values())
+                .collect(),
+        })
+    }
+
+    /// Describe the change of one file.  A missing side is an added or
+    /// removed file.
+    fn diff_dirs_file(
+        &self,
+        old: Option<(&String, &TreeFile)>,
+        new: Option<(&String, &TreeFile)>,
+    ) -> FilePatch {
+        let old_text = old.map_or("", |(_, file)| file.text.as_str());
+        let new_text = new.map_or("", |(_, file)| file.text.as_str());
+        let mut file = FilePatch::new(
+            old.map(|(path, _)| path.clone()),
+            new.map(|(path, _)| path.clone()),
+            old_text,
+            self.patch_make1(old_text, new_text),
+        );
+        file.old_mode = old.and_then(|(_, file)| file.mode);
+        file.new_mode = new.and_then(|(_, file)| file.mode);
+        file
+    }
+
+    /// Replay a patch set onto a directory tree.  Every file is checked
+    /// against its base-hash and only written when all of its patches
+    /// apply, otherwise it is left as it was.  Paths which are absolute,
+    /// have empty parts, contain ".." or pass a symbolic link are refused,
+    /// as are added or renamed files whose new path already exists.
+    ///
+    /// Args:
+    ///     patch_set: PatchSet object, e.g. from diff_dirs.
+    ///     root: Root of the tree to patch.
+    ///
+    /// Returns:
+    ///     One FileResult per file of the patch set.
+    pub fn apply_dirs(&self, patch_set: &PatchSet, root: &Path) -> Vec<FileResult> {
+        patch_set
+            .files
+            .iter()
+            .map(|file| FileResult {
+                path: file
+                    .new_path
+                    .clone()
+                    .or_else(|| file.old_path.clone())
+                    .unwrap_or_default(),
+                result: self.apply_dirs_file(file, root),
+            })
+            .collect()
+    }
+
+    fn apply_dirs_file(&self, file: &FilePatch, root: &Path) -> Result<Vec<bool>, ApplyDirError> {
+        let old_path = match &file.old_path {
+            Some(path) => Some(join_path(root, path)?),
+            None => None,
+        };
+        let new_path = match &file.new_path {
+            Some(path) => Some(join_path(root, path)?),
+            None => None,
+        };
+
+        let text = match &old_path {
+            Some(path) => fs::read_to_string(path)?,
+            None => "".to_string(),
+        };
+        if let Some(path) = new_path
+            .as_ref()
+            .filter(|path| old_path.as_ref() != Some(*path))
+        {
+            // Added and renamed files never overwrite another one.
+            if path.exists() {
+                return Err(
+                    io::Error::new(io::ErrorKind::AlreadyExists, "file already exists").into(),
+                );
+            }
+        }
+        let (text, results) = self.patch_set_apply(file, &text)?;
+        if results.contains(&false) {
+            return Ok(results);
+        }
+
+        match &new_path {
+            Some(path) => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, text)?;
+                if let Some(mode) = file.new_mode {
+                    set_file_mode(path, mode)?;
+                }
+                if let Some(old_path) = old_path.filter(|old_path| old_path != path) {
+                    fs::remove_file(old_path)?;
+                }
+            }
+            None => {
+                if let Some(old_path) = old_path {
+                    fs::remove_file(old_path)?;
+                }
+            }
+        }
+        Ok(results)
+    }
+}
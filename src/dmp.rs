@@ -11,20 +11,26 @@ use std::error::Error;
 use std::fmt::{self, Display};
 use std::iter::FromIterator;
 use std::result::Result;
-use std::time::Instant;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
+use super::normalize::{IgnoreSpace, Normalization};
 use super::percent_encoding::percent_decode_u16;
+use super::scoring::{BoundaryScorer, BoundaryScoring};
 
 use url::percent_encoding::{percent_decode, utf8_percent_encode, USERINFO_ENCODE_SET};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LengthUnit {
     UnicodeScalar,
     UTF16,
+    UTF8,
 }
 
+#[derive(Clone)]
 pub struct Dmp {
-    // Number of seconds to map a diff before giving up (None for infinity).
-    pub diff_timeout: Option<f32>,
+    // How long to map a diff before giving up (None for infinity).
+    pub diff_timeout: Option<Duration>,
     // Cost of an empty edit operation() in terms of edit characters.
     pub edit_cost: usize,
     /*How far to search for a match (0 = exact location, 1000+ = broad match).
@@ -45,8 +51,47 @@ pub struct Dmp {
     1.0 = very loose).  Note that Match_Threshold controls how closely the
     end points of a delete need to match.*/
     pub patch_delete_threshold: f32,
+    /*Compare text without regard to letter case during diff and match.
+    The returned diffs still carry the original text: text which compares
+    equal but is spelled differently is deleted and inserted again.*/
+    pub ignore_case: bool,
+    // Unicode normalization form applied before comparing text.
+    pub normalization: Normalization,
+    // Ignore whitespace entirely during diff and match.
+    pub ignore_whitespace: bool,
+    // Which whitespace differences to ignore when comparing lines.
+    pub ignore_space: IgnoreSpace,
+    // Ignore lines which are blank when comparing lines.
+    pub ignore_blank_lines: bool,
+    /*How similar a removed and an added file have to be to count as a rename
+    (0.0 = anything goes, 1.0 = identical), 0.5 is git's -M50%.*/
+    pub rename_threshold: f32,
+    // Where diff_cleanup_semantic_lossless prefers edits to start and end.
+    pub boundary_scoring: BoundaryScoring,
 }
 
+/// Error raised when parsing malformed patch text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(message: &str) -> ParseError {
+        ParseError {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for ParseError {}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Diff {
     Add(String),
@@ -86,6 +131,7 @@ impl Diff {
             Diff::Delete(_) => Diff::Delete(text),
         };
     }
+    #[allow(clippy::ptr_arg)]
     pub fn append_text(&mut self, text: &String) {
         *self = match self {
             Diff::Add(t) => Diff::Add(t.clone() + text),
@@ -93,6 +139,24 @@ impl Diff {
             Diff::Delete(t) => Diff::Delete(t.clone() + text),
         };
     }
+    /// Operation of the diff as the other ports number it: -1 for a
+    /// deletion, 0 for an equality and 1 for an insertion.
+    pub fn op(&self) -> i32 {
+        match self {
+            Diff::Delete(_) => -1,
+            Diff::Keep(_) => 0,
+            Diff::Add(_) => 1,
+        }
+    }
+    /// Build a diff from an operation numbered like op() returns it.
+    pub fn from_op(op: i32, text: String) -> Option<Diff> {
+        match op {
+            -1 => Some(Diff::Delete(text)),
+            0 => Some(Diff::Keep(text)),
+            1 => Some(Diff::Add(text)),
+            _ => None,
+        }
+    }
 }
 
 impl Patch {
@@ -113,6 +177,29 @@ impl Patch {
     }
 }
 
+/// When a diff in progress has to give up: once diff_timeout has passed
+/// since start, or once cancel is set.
+#[derive(Clone, Copy)]
+pub(crate) struct Deadline<'a> {
+    start: Instant,
+    cancel: Option<&'a AtomicBool>,
+}
+
+impl Deadline<'_> {
+    pub(crate) fn now() -> Self {
+        Deadline {
+            start: Instant::now(),
+            cancel: None,
+        }
+    }
+
+    pub(crate) fn expired(&self, timeout: Option<Duration>) -> bool {
+        self.cancel
+            .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+            || timeout.is_some_and(|timeout| self.start.elapsed() >= timeout)
+    }
+}
+
 /// return minimum element.
 fn min1(x: f32, y: f32) -> f32 {
     if x > y {
@@ -131,9 +218,62 @@ fn find_char(cha: char, text: &[char], start: usize) -> i32 {
         .unwrap_or(-1)
 }
 
+impl LengthUnit {
+    /// Length of a char in this unit.
+    pub fn char_len(self, ch: char) -> usize {
+        match self {
+            LengthUnit::UnicodeScalar => 1,
+            LengthUnit::UTF16 => ch.len_utf16(),
+            LengthUnit::UTF8 => ch.len_utf8(),
+        }
+    }
+
+    /// Length of a text in this unit.
+    pub fn text_len(self, text: &str) -> usize {
+        match self {
+            LengthUnit::UnicodeScalar => text.chars().count(),
+            LengthUnit::UTF16 => text.encode_utf16().count(),
+            LengthUnit::UTF8 => text.len(),
+        }
+    }
+
+    /// Length of a slice of chars in this unit.
+    pub fn chars_len(self, chars: &[char]) -> usize {
+        match self {
+            LengthUnit::UnicodeScalar => chars.len(),
+            _ => chars.iter().map(|ch| self.char_len(*ch)).sum(),
+        }
+    }
+
+    /// Convert an offset in this unit into an index into chars.  Offsets
+    /// inside a char round down, offsets past the end stay past the end.
+    fn to_char_index(self, chars: &[char], loc: i32) -> i32 {
+        if self == LengthUnit::UnicodeScalar || loc <= 0 {
+            return loc;
+        }
+        let mut offset = 0;
+        for (i, ch) in chars.iter().enumerate() {
+            offset += self.char_len(*ch) as i32;
+            if offset > loc {
+                return i as i32;
+            }
+        }
+        chars.len() as i32 + (loc - offset)
+    }
+
+    /// Convert an index into chars into an offset in this unit.
+    fn to_unit_index(self, chars: &[char], index: i32) -> i32 {
+        if self == LengthUnit::UnicodeScalar || index <= 0 {
+            return index;
+        }
+        let end = min(index as usize, chars.len());
+        (self.chars_len(&chars[..end]) + (index as usize - end)) as i32
+    }
+}
+
 trait StringView {
     fn len(&self) -> usize;
-    fn slice(&self, range: std::ops::Range<usize>) -> Result<String, std::string::FromUtf16Error>;
+    fn slice(&self, range: std::ops::Range<usize>) -> Result<String, ParseError>;
 }
 
 struct StringScalarView {
@@ -153,8 +293,9 @@ impl StringView for StringScalarView {
         self.text.len()
     }
 
-    fn slice(&self, range: std::ops::Range<usize>) -> Result<String, std::string::FromUtf16Error> {
-        Ok((&self.text)[range].iter().collect())
+    fn slice(&self, range: std::ops::Range<usize>) -> Result<String, ParseError> {
+        let text = self.text.get(range).ok_or_else(delta_too_long)?;
+        Ok(text.iter().collect())
     }
 }
 
@@ -175,11 +316,52 @@ impl StringView for StringUTF16View {
         self.text.len()
     }
 
-    fn slice(&self, range: std::ops::Range<usize>) -> Result<String, std::string::FromUtf16Error> {
-        String::from_utf16(&self.text[range])
+    fn slice(&self, range: std::ops::Range<usize>) -> Result<String, ParseError> {
+        let text = self.text.get(range).ok_or_else(delta_too_long)?;
+        String::from_utf16(text).map_err(|_| ParseError::new("delta splits a surrogate pair"))
+    }
+}
+
+struct StringUTF8View<'a> {
+    text: &'a str,
+}
+
+impl<'a> StringUTF8View<'a> {
+    pub fn new(text: &'a str) -> StringUTF8View<'a> {
+        StringUTF8View { text }
     }
 }
 
+impl StringView for StringUTF8View<'_> {
+    fn len(&self) -> usize {
+        self.text.len()
+    }
+
+    fn slice(&self, range: std::ops::Range<usize>) -> Result<String, ParseError> {
+        let text = self.text.as_bytes().get(range).ok_or_else(delta_too_long)?;
+        String::from_utf8(text.to_vec()).map_err(|_| ParseError::new("delta splits a char"))
+    }
+}
+
+fn delta_too_long() -> ParseError {
+    ParseError::new("delta is longer than the source text")
+}
+
+/// Split a delta token into its operation and the rest.
+fn delta_token(token: &str) -> Result<(char, &str), ParseError> {
+    let mut chars = token.chars();
+    match chars.next() {
+        Some(op @ ('+' | '-' | '=')) => Ok((op, chars.as_str())),
+        _ => Err(ParseError::new("invalid diff operation in delta")),
+    }
+}
+
+fn delta_length(content: &str) -> Result<usize, ParseError> {
+    content
+        .parse::<usize>()
+        .map_err(|_| ParseError::new("invalid number in delta"))
+}
+
 impl Default for Dmp {
     fn default() -> Self {
         Self {
@@ -190,6 +372,13 @@ impl Default for Dmp {
             patch_margin: 4,
             match_maxbits: 32,
             match_threshold: 0.5,
+            ignore_case: false,
+            normalization: Normalization::None,
+            ignore_whitespace: false,
+            ignore_space: IgnoreSpace::None,
+            ignore_blank_lines: false,
+            rename_threshold: 0.5,
+            boundary_scoring: BoundaryScoring::Prose,
         }
     }
 }
@@ -207,15 +396,59 @@ impl Dmp {
     /// Returns:
     ///     Vector of diffs as changes.
     pub fn diff_main(&self, text1: &str, text2: &str, checklines: bool) -> Vec<Diff> {
-        self.diff_main_internal(text1, text2, checklines, Instant::now())
+        self.diff_main_deadline(text1, text2, checklines, Deadline::now())
     }
 
-    fn diff_main_internal(
+    /// Like diff_main, but gives up as soon as cancel is set, as if
+    /// diff_timeout had run out.  For diffs running on another thread.
+    ///
+    /// Args:
+    ///     text1: Old text to be diffed.
+    ///     text2: New text to be diffed.
+    ///     checklines: Speedup flag, as for diff_main.
+    ///     cancel: Flag raised by whoever no longer wants the result.
+    /// Returns:
+    ///     Vector of diffs as changes, valid but not minimal if cancelled.
+    pub fn diff_main_cancellable(
         &self,
         text1: &str,
         text2: &str,
         checklines: bool,
-        start_time: Instant,
+        cancel: &AtomicBool,
+    ) -> Vec<Diff> {
+        let deadline = Deadline {
+            cancel: Some(cancel),
+            ..Deadline::now()
+        };
+        self.diff_main_deadline(text1, text2, checklines, deadline)
+    }
+
+    fn diff_main_deadline(
+        &self,
+        text1: &str,
+        text2: &str,
+        checklines: bool,
+        deadline: Deadline<'_>,
+    ) -> Vec<Diff> {
+        if checklines && self.compare_lines_normalized() {
+            let text1: Vec<char> = text1.chars().collect();
+            let text2: Vec<char> = text2.chars().collect();
+            let mut diffs = self.diff_linemode_internal(&text1, &text2, deadline);
+            self.diff_cleanup_merge(&mut diffs);
+            return diffs;
+        }
+        if self.compare_normalized() {
+            return self.diff_main_normalized(text1, text2, deadline);
+        }
+        self.diff_main_internal(text1, text2, checklines, deadline)
+    }
+
+    pub(crate) fn diff_main_internal(
+        &self,
+        text1: &str,
+        text2: &str,
+        checklines: bool,
+        deadline: Deadline<'_>,
     ) -> Vec<Diff> {
         match (text1.is_empty(), text2.is_empty()) {
             (true, true) => {
@@ -253,7 +486,7 @@ impl Dmp {
         }
 
         // Compute the diff on the middle block.
-        let middle_diffs = self.diff_compute(text1, text2, checklines, start_time);
+        let middle_diffs = self.diff_compute(text1, text2, checklines, deadline);
         diffs.extend(middle_diffs);
 
         // Restore the suffix
@@ -281,7 +514,7 @@ impl Dmp {
         text1: &[char],
         text2: &[char],
         checklines: bool,
-        start_time: Instant,
+        deadline: Deadline<'_>,
     ) -> Vec<Diff> {
         let mut diffs: Vec<Diff> = Vec::new();
         if text1.is_empty() {
@@ -338,9 +571,9 @@ impl Dmp {
             // A half-match was found, sort out the return data.
             // Send both pairs off for separate processing.
             let mut diffs_a =
-                self.diff_main_internal(text1_a.as_str(), text2_a.as_str(), checklines, start_time);
+                self.diff_main_internal(text1_a.as_str(), text2_a.as_str(), checklines, deadline);
             let diffs_b =
-                self.diff_main_internal(text1_b.as_str(), text2_b.as_str(), checklines, start_time);
+                self.diff_main_internal(text1_b.as_str(), text2_b.as_str(), checklines, deadline);
             diffs_a.push(Diff::Keep(mid_common));
             // Merge the result.
             for x in diffs_b {
@@ -350,9 +583,9 @@ impl Dmp {
         }
 
         if checklines && text1.len() > 100 && text2.len() > 100 {
-            return self.diff_linemode_internal(text1, text2, start_time);
+            return self.diff_linemode_internal(text1, text2, deadline);
         }
-        self.diff_bisect_internal(text1, text2, start_time)
+        self.diff_bisect_internal(text1, text2, deadline)
     }
 
     /// Find the first index after a specific index in text1 where patern is present.
@@ -475,24 +708,66 @@ impl Dmp {
     /// Returns:
     ///     Vector of diffs as changes.
     pub fn diff_linemode(&self, text1: &[char], text2: &[char]) -> Vec<Diff> {
-        self.diff_linemode_internal(text1, text2, Instant::now())
+        self.diff_linemode_internal(text1, text2, Deadline::now())
+    }
+
+    /// Find the differences between two texts line by line, without
+    /// refining changed lines character by character.  Lines are compared
+    /// with the ignore_space, ignore_blank_lines, ignore_case and
+    /// normalization options.  Lines which compare equal but are
+    /// spelled differently are deleted and inserted again.
+    ///
+    /// Args:
+    ///     text1: Old text to be diffed.
+    ///     text2: New text to be diffed.
+    ///
+    /// Returns:
+    ///     Vector of diffs made of whole lines.
+    pub fn diff_lines(&self, text1: &str, text2: &str) -> Vec<Diff> {
+        self.diff_lines_internal(text1, text2, Deadline::now())
+    }
+
+    /// Split a line-mode diff into diffs of exactly one line each.
+    /// Line terminators stay with their line.
+    ///
+    /// Args:
+    ///     diffs: Vector of diffs made of whole lines.
+    ///
+    /// Returns:
+    ///     Vector of single line diffs.
+    pub fn diff_split_lines(&self, diffs: &[Diff]) -> Vec<Diff> {
+        let mut lines: Vec<Diff> = vec![];
+        for diff in diffs {
+            for line in diff.text().split_inclusive('\n') {
+                lines.push(diff.with_text(line.to_string()));
+            }
+        }
+        lines
     }
 
     fn diff_linemode_internal(
         &self,
         text1: &[char],
         text2: &[char],
-        start_time: Instant,
+        deadline: Deadline<'_>,
     ) -> Vec<Diff> {
         // Scan the text on a line-by-line basis first.
-        let (text3, text4, linearray) = self.diff_lines_tochars(text1, text2);
+        let mut diffs: Vec<Diff> = if self.compare_lines_normalized() || self.compare_normalized() {
+            // Equal lines may be spelled differently, compare them by key.
+            let text1: String = text1.iter().collect();
+            let text2: String = text2.iter().collect();
+            self.diff_lines_internal(&text1, &text2, deadline)
+        } else {
+            let (text3, text4, linearray) = self.diff_lines_tochars(text1, text2);
 
-        let dmp = Dmp::default();
-        let mut diffs: Vec<Diff> =
-            dmp.diff_main_internal(text3.as_str(), text4.as_str(), false, start_time);
+            let dmp = Dmp::default();
+            let mut diffs: Vec<Diff> =
+                dmp.diff_main_internal(text3.as_str(), text4.as_str(), false, deadline);
 
-        // Convert the diff back to original text.
-        self.diff_chars_tolines(&mut diffs, &linearray);
+            // Convert the diff back to original text.
+            self.diff_chars_tolines(&mut diffs, &linearray);
+            diffs
+        };
         // Eliminate freak matches (e.g. blank lines)
         self.diff_cleanup_semantic(&mut diffs);
 
@@ -519,12 +794,20 @@ impl Dmp {
                     // Upon reaching an equality, check for prior redundancies.
                     if count_delete >= 1 && count_insert >= 1 {
                         // Delete the offending records and add the merged ones.
-                        let sub_diff = self.diff_main_internal(
-                            text_delete.as_str(),
-                            text_insert.as_str(),
-                            false,
-                            start_time,
-                        );
+                        let sub_diff = if self.compare_normalized() {
+                            self.diff_main_normalized(
+                                text_delete.as_str(),
+                                text_insert.as_str(),
+                                deadline,
+                            )
+                        } else {
+                            self.diff_main_internal(
+                                text_delete.as_str(),
+                                text_insert.as_str(),
+                                false,
+                                deadline,
+                            )
+                        };
                         for z in sub_diff {
                             temp.push(z);
                         }
@@ -561,14 +844,14 @@ impl Dmp {
     /// Returns:
     ///         Vector of diffs as changes.
     pub fn diff_bisect(&self, char1: &[char], char2: &[char]) -> Vec<Diff> {
-        self.diff_bisect_internal(char1, char2, Instant::now())
+        self.diff_bisect_internal(char1, char2, Deadline::now())
     }
 
     fn diff_bisect_internal(
         &self,
         char1: &[char],
         char2: &[char],
-        start_time: Instant,
+        deadline: Deadline<'_>,
     ) -> Vec<Diff> {
         let text1_length = char1.len() as i32;
         let text2_length = char2.len() as i32;
@@ -590,9 +873,7 @@ impl Dmp {
         let mut k2start: i32 = 0;
         let mut k2end: i32 = 0;
         for d in 0..max_d {
-            if self.diff_timeout.is_some()
-                && start_time.elapsed().as_secs_f32() >= self.diff_timeout.unwrap()
-            {
+            if deadline.expired(self.diff_timeout) {
                 break;
             }
 
@@ -637,7 +918,7 @@ impl Dmp {
                         x2 = text1_length - v2[k2_offset as usize];
                         if x1 >= x2 {
                             // Overlap detected.
-                            return self.diff_bisect_split(char1, char2, x1, y1, start_time);
+                            return self.diff_bisect_split(char1, char2, x1, y1, deadline);
                         }
                     }
                 }
@@ -689,7 +970,7 @@ impl Dmp {
                         x2 = text1_length - x2;
                         if x1 >= x2 {
                             // Overlap detected.
-                            return self.diff_bisect_split(char1, char2, x1, y1, start_time);
+                            return self.diff_bisect_split(char1, char2, x1, y1, deadline);
                         }
                     }
                 }
@@ -720,7 +1001,7 @@ impl Dmp {
         text2: &[char],
         x: i32,
         y: i32,
-        start_time: Instant,
+        deadline: Deadline<'_>,
     ) -> Vec<Diff> {
         let text1a: String = text1[..(x as usize)].iter().collect();
         let text2a: String = text2[..(y as usize)].iter().collect();
@@ -728,10 +1009,8 @@ impl Dmp {
         let text2b: String = text2[(y as usize)..].iter().collect();
 
         // Compute both diffs serially.
-        let mut diffs =
-            self.diff_main_internal(text1a.as_str(), text2a.as_str(), false, start_time);
-        let mut diffsb =
-            self.diff_main_internal(text1b.as_str(), text2b.as_str(), false, start_time);
+        let mut diffs = self.diff_main_internal(text1a.as_str(), text2a.as_str(), false, deadline);
+        let mut diffsb = self.diff_main_internal(text1b.as_str(), text2b.as_str(), false, deadline);
         diffs.append(&mut diffsb);
         diffs
     }
@@ -823,13 +1102,15 @@ impl Dmp {
         let mut linearray: Vec<String> = vec!["".to_string()];
         let mut linehash: HashMap<String, i32> = HashMap::new();
         let chars1 = self.diff_lines_tochars_munge(text1, &mut linearray, &mut linehash);
-        let dmp = Dmp::default();
-        let chars2 = dmp.diff_lines_tochars_munge(text2, &mut linearray, &mut linehash);
+        let chars2 = self.diff_lines_tochars_munge(text2, &mut linearray, &mut linehash);
         (chars1, chars2, linearray)
     }
 
     /// Split a text into an array of strings.  Reduce the texts to a string
     /// of hashes where each Unicode character represents one line.
+    /// Lines are hashed exactly, so diff_chars_tolines gives back the
+    /// spelling of either side; lines compared with the ignore_space
+    /// options go through diff_lines instead.
     /// Modifies linearray and linehash through being a closure.
     ///
     /// Args:
@@ -1019,6 +1300,7 @@ impl Dmp {
     /// Five element Vector, containing the prefix of text1, the suffix of text1,
     /// the prefix of text2, the suffix of text2 and the common middle.  Or empty vector
     /// if there was no match.
+    #[allow(clippy::manual_div_ceil)]
     pub fn diff_half_match(&self, text1: &[char], text2: &[char]) -> Option<[String; 5]> {
         self.diff_timeout?;
 
@@ -1303,8 +1585,8 @@ impl Dmp {
                 best_equality1 = equality1.clone();
                 best_edit = edit;
                 best_equality2 = equality2;
-                best_score = self.diff_cleanup_semantic_score(&equality1_vec, &edit_vec)
-                    + self.diff_cleanup_semantic_score(&edit_vec, &equality2_vec);
+                best_score = self.boundary_scoring.score(&equality1_vec, &edit_vec)
+                    + self.boundary_scoring.score(&edit_vec, &equality2_vec);
                 let edit_len = edit_vec.len();
                 let mut equality2_len = equality2_vec.len();
                 while equality2_len > 0 && edit_len > 0 {
@@ -1317,8 +1599,8 @@ impl Dmp {
                     edit_vec = edit_vec[1..].to_vec();
                     equality2_len -= 1;
                     equality2_vec = equality2_vec[1..].to_vec();
-                    score = self.diff_cleanup_semantic_score(&equality1_vec, &edit_vec)
-                        + self.diff_cleanup_semantic_score(&edit_vec, &equality2_vec);
+                    score = self.boundary_scoring.score(&equality1_vec, &edit_vec)
+                        + self.boundary_scoring.score(&edit_vec, &equality2_vec);
                     // The >= encourages trailing rather than leading whitespace on edits.
                     if score >= best_score {
                         best_score = score;
@@ -1361,7 +1643,7 @@ impl Dmp {
     ///
     /// Returns:
     ///     The score.
-    fn diff_cleanup_semantic_score(&self, one: &[char], two: &[char]) -> i32 {
+    pub(crate) fn diff_cleanup_semantic_score(one: &[char], two: &[char]) -> i32 {
         if one.is_empty() || two.is_empty() {
             // Edges are the best.
             return 6;
@@ -1728,6 +2010,20 @@ impl Dmp {
     /// Returns:
     ///     Location within text2.
     pub fn diff_xindex(&self, diffs: &Vec<Diff>, loc: i32) -> i32 {
+        self.diff_xindex_unit(diffs, loc, LengthUnit::UnicodeScalar)
+    }
+
+    /// loc is a location in text1, compute and return the equivalent location
+    /// in text2, both counted in length_unit.
+    ///
+    /// Args:
+    ///     diffs: Vector of diff object.
+    ///     loc: Location within text1.
+    ///     length_unit: Unit of loc and of the result.
+    ///
+    /// Returns:
+    ///     Location within text2.
+    pub fn diff_xindex_unit(&self, diffs: &Vec<Diff>, loc: i32, length_unit: LengthUnit) -> i32 {
         let mut chars1 = 0;
         let mut chars2 = 0;
         let mut last_chars1 = 0;
@@ -1737,11 +2033,11 @@ impl Dmp {
         for diffs_item in diffs {
             if let Diff::Keep(txt) | Diff::Delete(txt) = &diffs_item {
                 // Equality or deletion.
-                chars1 += txt.len() as i32;
+                chars1 += length_unit.text_len(txt) as i32;
             }
             if let Diff::Keep(txt) | Diff::Add(txt) = &diffs_item {
                 // Equality or insertion.
-                chars2 += txt.len() as i32;
+                chars2 += length_unit.text_len(txt) as i32;
             }
             if chars1 > loc {
                 // Overshot the location.
@@ -1759,6 +2055,33 @@ impl Dmp {
         last_chars2 + (loc - last_chars1)
     }
 
+    /// Convert a diff array into a pretty HTML report.
+    ///
+    /// Args:
+    ///     diffs: Vector of diff object.
+    ///
+    /// Returns:
+    ///     HTML representation.
+    pub fn diff_pretty_html(&self, diffs: &[Diff]) -> String {
+        let mut html = "".to_string();
+        for diff in diffs {
+            let text = diff
+                .text()
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('\n', "&para;<br>");
+            match diff {
+                Diff::Add(_) => html += &format!("<ins style=\"background:#e6ffe6;\">{text}</ins>"),
+                Diff::Delete(_) => {
+                    html += &format!("<del style=\"background:#ffe6e6;\">{text}</del>")
+                }
+                Diff::Keep(_) => html += &format!("<span>{text}</span>"),
+            }
+        }
+        html
+    }
+
     /// Compute and return the source text (all equalities and deletions).
     ///
     /// Args:
@@ -1803,29 +2126,35 @@ impl Dmp {
     /// Returns:
     ///     Destination text
     pub fn diff_text2_from_delta_u16(&self, text1: &str, delta: &str) -> String {
+        self.diff_text2_parse_delta_u16(text1, delta)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    fn diff_text2_parse_delta_u16(&self, text1: &str, delta: &str) -> Result<String, ParseError> {
         let text1_u16: Vec<u16> = text1.encode_utf16().collect();
         let mut text2_u16: Vec<u16> = Vec::new();
 
         let tokens: Vec<&str> = (*delta).split('\t').collect();
 
-        let mut text_offset = 0;
+        let mut text_offset: usize = 0;
         for token in tokens {
             if token.is_empty() {
                 continue;
             }
 
-            let operation = &token[0..1];
-            let operation_content = &token[1..];
+            let (operation, operation_content) = delta_token(token)?;
 
-            if operation == "+" {
-                let decoded = percent_decode_u16(operation_content.as_bytes()).unwrap();
+            if operation == '+' {
+                let decoded = percent_decode_u16(operation_content.as_bytes())
+                    .map_err(|_| ParseError::new("malformed text in delta"))?;
                 text2_u16.extend(decoded);
             } else {
-                let content_length = operation_content.parse::<usize>().unwrap();
+                let content_length = delta_length(operation_content)?;
+                let range = text_offset..text_offset.saturating_add(content_length);
+                let text = text1_u16.get(range).ok_or_else(delta_too_long)?;
 
-                if operation == "=" {
-                    let range = text_offset..(content_length + text_offset);
-                    text2_u16.extend(&text1_u16[range]);
+                if operation == '=' {
+                    text2_u16.extend(text);
                 }
 
                 text_offset += content_length;
@@ -1834,10 +2163,10 @@ impl Dmp {
 
         // we should have consumed all text
         if text1_u16.len() != text_offset {
-            panic!("wrong patern or text");
+            return Err(ParseError::new("delta is shorter than the source text"));
         }
 
-        String::from_utf16(&text2_u16).unwrap()
+        String::from_utf16(&text2_u16).map_err(|_| ParseError::new("delta splits a surrogate pair"))
     }
 
     /// Compute the Levenshtein distance; the number of inserted, deleted or
@@ -1849,16 +2178,28 @@ impl Dmp {
     /// Returns:
     ///     Number of changes.
     pub fn diff_levenshtein(&self, diffs: &Vec<Diff>) -> i32 {
+        self.diff_levenshtein_unit(diffs, LengthUnit::UnicodeScalar)
+    }
+
+    /// Compute the Levenshtein distance, counted in length_unit.
+    ///
+    /// Args:
+    ///     diffs: Vector of diff object.
+    ///     length_unit: Unit of the result.
+    ///
+    /// Returns:
+    ///     Number of changes.
+    pub fn diff_levenshtein_unit(&self, diffs: &Vec<Diff>, length_unit: LengthUnit) -> i32 {
         let mut levenshtein = 0;
         let mut insertions = 0;
         let mut deletions = 0;
         for adiff in diffs {
             match &adiff {
                 Diff::Add(txt) => {
-                    insertions += txt.len();
+                    insertions += length_unit.text_len(txt);
                 }
                 Diff::Delete(txt) => {
-                    deletions += txt.len();
+                    deletions += length_unit.text_len(txt);
                 }
                 Diff::Keep(_) => {
                     // A deletion and an insertion is one substitution.
@@ -1887,6 +2228,7 @@ impl Dmp {
     ///         For example diff from "🅰🅱" -> "🅱" can have different delta:
     ///         * When operating on unicode scalars delta will be "-1\t=1"
     ///         * For UTF-16 delta will be "-2\t=2"
+    ///         * For UTF-8 delta will be "-4\t=4"
     ///
     /// Returns:
     ///     Delta text.
@@ -1913,6 +2255,9 @@ impl Dmp {
                     }
                     if is {
                         continue;
+                    } else if *temp4_item == '%' {
+                        text += "%25";
+                        continue;
                     }
                     let mut temp6 = "".to_string();
                     temp6.push(*temp4_item);
@@ -1926,10 +2271,7 @@ impl Dmp {
                     text += "=";
                 }
 
-                let count: usize = match length_unit {
-                    LengthUnit::UnicodeScalar => diffs_item.text().chars().count(),
-                    LengthUnit::UTF16 => diffs_item.text().encode_utf16().count(),
-                };
+                let count: usize = length_unit.text_len(diffs_item.text());
                 text += count.to_string().as_str();
             }
 
@@ -1963,20 +2305,47 @@ impl Dmp {
         delta: &str,
         length_unit: LengthUnit,
     ) -> Vec<Diff> {
+        self.diff_parse_delta(text1, delta, length_unit)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Given the original text1, and an encoded string which describes the
+    /// operations required to transform text1 into text2, compute the full
+    /// diff, reporting a malformed delta as an error instead of panicking.
+    ///
+    /// Args:
+    ///     text1: Source string for the diff.
+    ///     delta: Delta text.
+    ///     length_unit: Unit of length used in delta.
+    ///
+    /// Returns:
+    ///     Vector of diff object or a ParseError.
+    pub fn diff_parse_delta(
+        &self,
+        text1: &str,
+        delta: &str,
+        length_unit: LengthUnit,
+    ) -> Result<Vec<Diff>, ParseError> {
         match length_unit {
             LengthUnit::UnicodeScalar => {
                 let text = StringScalarView::new(text1);
-                self.diff_from_delta_string_view(&text, delta).unwrap()
+                self.diff_from_delta_string_view(&text, delta)
             }
             LengthUnit::UTF16 => {
                 let text = StringUTF16View::new(text1);
-                match self.diff_from_delta_string_view(&text, delta) {
-                    Ok(diff) => diff,
-                    Err(_) => {
-                        let text2 = self.diff_text2_from_delta_u16(text1, delta);
-                        self.diff_main(text1, &text2, true)
-                    }
-                }
+                self.diff_from_delta_string_view(&text, delta)
+                    .or_else(|err| {
+                        // Deltas of the JavaScript and Java ports may split a
+                        // surrogate pair, rebuild the diff from the new text.
+                        let text2 = self
+                            .diff_text2_parse_delta_u16(text1, delta)
+                            .map_err(|_| err)?;
+                        Ok(self.diff_main(text1, &text2, true))
+                    })
+            }
+            LengthUnit::UTF8 => {
+                let text = StringUTF8View::new(text1);
+                self.diff_from_delta_string_view(&text, delta)
             }
         }
     }
@@ -1985,30 +2354,30 @@ impl Dmp {
         &self,
         text1: &impl StringView,
         delta: &str,
-    ) -> Result<Vec<Diff>, Box<dyn Error>> {
+    ) -> Result<Vec<Diff>, ParseError> {
         let mut diffs: Vec<Diff> = vec![];
         let tokens: Vec<&str> = (*delta).split('\t').collect();
 
-        let mut text_offset = 0;
+        let mut text_offset: usize = 0;
         for token in tokens {
             if token.is_empty() {
                 continue;
             }
 
-            let operation = &token[0..1];
-            let operation_content = &token[1..];
+            let (operation, operation_content) = delta_token(token)?;
 
-            if operation == "+" {
+            if operation == '+' {
                 let text = percent_decode(operation_content.as_bytes())
-                    .decode_utf8()?
+                    .decode_utf8()
+                    .map_err(|_| ParseError::new("malformed text in delta"))?
                     .to_string();
                 diffs.push(Diff::Add(text));
             } else {
-                let content_length = operation_content.parse::<usize>().unwrap();
-                let range = text_offset..(content_length + text_offset);
+                let content_length = delta_length(operation_content)?;
+                let range = text_offset..text_offset.saturating_add(content_length);
 
                 let text = text1.slice(range)?;
-                diffs.push(if operation == "=" {
+                diffs.push(if operation == '=' {
                     Diff::Keep(text)
                 } else {
                     Diff::Delete(text)
@@ -2020,7 +2389,7 @@ impl Dmp {
 
         // we should have consumed all text
         if text1.len() != text_offset {
-            panic!("wrong patern or text");
+            return Err(ParseError::new("delta is shorter than the source text"));
         }
 
         Ok(diffs)
@@ -2035,8 +2404,53 @@ impl Dmp {
     ///
     /// Returns:
     ///     Best match index or -1.
-    pub fn match_main(&self, text1: &str, patern1: &str, mut loc: i32) -> i32 {
-        loc = max(0, min(loc, text1.len() as i32));
+    pub fn match_main(&self, text1: &str, patern1: &str, loc: i32) -> i32 {
+        if self.compare_normalized() {
+            return self.match_main_normalized(text1, patern1, loc);
+        }
+        self.match_main_internal(text1, patern1, loc)
+    }
+
+    /// Locate the best instance of 'pattern' in 'text' near 'loc', with
+    /// 'loc' and the result counted in length_unit.
+    ///
+    /// Args:
+    ///     text: The text to search.
+    ///     pattern: The pattern to search for.
+    ///     loc: The location to search around.
+    ///     length_unit: Unit of loc and of the result.
+    ///
+    /// Returns:
+    ///     Best match index or -1.
+    pub fn match_main_unit(
+        &self,
+        text1: &str,
+        patern1: &str,
+        loc: i32,
+        length_unit: LengthUnit,
+    ) -> i32 {
+        let chars: Vec<char> = text1.chars().collect();
+        let index = self.match_main(text1, patern1, length_unit.to_char_index(&chars, loc));
+        length_unit.to_unit_index(&chars, index)
+    }
+
+    /// Locate the best instance of 'pattern' in 'text' near 'loc', with
+    /// 'loc' and the result in UTF-16 code units like the JavaScript and
+    /// Java ports.
+    ///
+    /// Args:
+    ///     text: The text to search.
+    ///     pattern: The pattern to search for.
+    ///     loc: The location to search around.
+    ///
+    /// Returns:
+    ///     Best match index or -1.
+    pub fn match_main_u16(&self, text1: &str, patern1: &str, loc: i32) -> i32 {
+        self.match_main_unit(text1, patern1, loc, LengthUnit::UTF16)
+    }
+
+    pub(crate) fn match_main_internal(&self, text1: &str, patern1: &str, mut loc: i32) -> i32 {
+        loc = max(0, min(loc, text1.chars().count() as i32));
         if patern1.is_empty() {
             return loc;
         }
@@ -2240,7 +2654,7 @@ impl Dmp {
         // matches are found, increase the pattern length.
         let mut rst = 0;
         while self.kmp(text, &pattern, 0) != self.rkmp(text, &pattern, text.len() - 1)
-            && pattern.len() < (self.match_maxbits - self.patch_margin * 2)
+            && pattern.len() < self.match_maxbits.saturating_sub(self.patch_margin * 2)
         {
             padding += self.patch_margin;
 
@@ -2356,7 +2770,7 @@ impl Dmp {
                     patch.diffs.push(diffs[i].clone());
                     let temp: Vec<char> = postpatch[char_count2..].to_vec();
                     postpatch = postpatch[..char_count2].to_vec();
-                    patch.length2 += txt.len();
+                    patch.length2 += txt.chars().count();
                     for ch in txt.chars() {
                         postpatch.push(ch);
                     }
@@ -2367,26 +2781,28 @@ impl Dmp {
                 Diff::Delete(txt) => {
                     // Deletion.
                     patch.diffs.push(diffs[i].clone());
-                    let temp: Vec<char> = postpatch[(txt.len() + char_count2)..].to_vec();
+                    let txt_len = txt.chars().count();
+                    let temp: Vec<char> = postpatch[(txt_len + char_count2)..].to_vec();
                     postpatch = postpatch[..char_count2].to_vec();
-                    patch.length1 += txt.len();
+                    patch.length1 += txt_len;
                     for ch in &temp {
                         postpatch.push(*ch);
                     }
                 }
                 Diff::Keep(txt) => {
-                    if txt.len() <= self.patch_margin * 2
+                    let txt_len = txt.chars().count();
+                    if txt_len <= self.patch_margin * 2
                         && !patch.diffs.is_empty()
                         && i != diffs.len() - 1
                     {
                         // Small equality inside a patch.
                         patch.diffs.push(diffs[i].clone());
-                        patch.length1 += txt.len();
-                        patch.length2 += txt.len();
+                        patch.length1 += txt_len;
+                        patch.length2 += txt_len;
                     }
 
                     // Time for a new patch.
-                    if txt.len() >= 2 * self.patch_margin && !patch.diffs.is_empty() {
+                    if txt_len >= 2 * self.patch_margin && !patch.diffs.is_empty() {
                         self.patch_add_context(&mut patch, &mut prepatch);
                         patches.push(patch);
                         patch = Patch::new(vec![], 0, 0, 0, 0);
@@ -2398,7 +2814,7 @@ impl Dmp {
 
             // Update the current character count.
             if let Diff::Keep(txt) | Diff::Delete(txt) = &diffs[i] {
-                char_count1 += txt.len();
+                char_count1 += txt.chars().count();
             }
             let temp1: &Vec<char> = &diffs[i].text().chars().collect();
             if let Diff::Keep(_) | Diff::Add(_) = &diffs[i] {
@@ -2415,6 +2831,108 @@ impl Dmp {
         patches
     }
 
+    /// Compute a list of patches to turn text1 into text2, with offsets and
+    /// lengths counted in length_unit.
+    ///
+    /// Args:
+    ///     text1: First string.
+    ///     diffs: Vector of diff object.
+    ///     length_unit: Unit of the patch offsets and lengths.
+    /// Returns:
+    ///     Array of Patch objects.
+    pub fn patch_make_unit(
+        &self,
+        text1: &str,
+        diffs: &mut [Diff],
+        length_unit: LengthUnit,
+    ) -> Vec<Patch> {
+        let mut patches = self.patch_make4(text1, diffs);
+        if length_unit == LengthUnit::UnicodeScalar {
+            return patches;
+        }
+        // Every patch starts where all patches before it have been applied,
+        // so the text in front of it is that of text2.
+        let text2: Vec<char> = diffs
+            .iter()
+            .filter(|diff| !matches!(diff, Diff::Delete(_)))
+            .flat_map(|diff| diff.text().chars())
+            .collect();
+        for patch in &mut patches {
+            patch.start1 = length_unit.chars_len(&text2[..min(patch.start1, text2.len())]);
+            patch.start2 = length_unit.chars_len(&text2[..min(patch.start2, text2.len())]);
+            self.patch_lengths_unit(patch, length_unit);
+        }
+        patches
+    }
+
+    /// Compute a list of patches to turn text1 into text2, with offsets and
+    /// lengths in UTF-16 code units as the JavaScript and Java ports count them.
+    ///
+    /// Args:
+    ///     text1: First string.
+    ///     text2: Second string.
+    /// Returns:
+    ///     Vector of Patch objects.
+    pub fn patch_make1_u16(&self, text1: &str, text2: &str) -> Vec<Patch> {
+        let mut diffs: Vec<Diff> = self.diff_main(text1, text2, true);
+        if diffs.len() > 2 {
+            self.diff_cleanup_semantic(&mut diffs);
+            self.diff_cleanup_efficiency(&mut diffs);
+        }
+        self.patch_make4_u16(text1, &mut diffs)
+    }
+
+    /// Compute a list of patches in UTF-16 code units, see patch_make1_u16.
+    ///
+    /// Args:
+    ///     diffs: Vector of diff object.
+    /// Returns:
+    ///     Vector of Patch objects.
+    pub fn patch_make2_u16(&self, diffs: &mut Vec<Diff>) -> Vec<Patch> {
+        let text1 = self.diff_text1(diffs);
+        self.patch_make4_u16(text1.as_str(), diffs)
+    }
+
+    /// Compute a list of patches in UTF-16 code units, see patch_make1_u16.
+    ///
+    /// Args:
+    ///     text1: First string.
+    ///     text2: Second string.
+    ///     diffs: Vector of diff.
+    /// Returns:
+    ///     Vector of Patch objects.
+    pub fn patch_make3_u16(&self, text1: &str, _text2: &str, diffs: &mut [Diff]) -> Vec<Patch> {
+        self.patch_make4_u16(text1, diffs)
+    }
+
+    /// Compute a list of patches in UTF-16 code units, see patch_make1_u16.
+    ///
+    /// Args:
+    ///     text1: First string.
+    ///     diffs: Vector of diff object.
+    /// Returns:
+    ///     Vector of Patch objects.
+    pub fn patch_make4_u16(&self, text1: &str, diffs: &mut [Diff]) -> Vec<Patch> {
+        self.patch_make_unit(text1, diffs, LengthUnit::UTF16)
+    }
+
+    /// Recount length1 and length2 of a patch from its diffs.
+    fn patch_lengths_unit(&self, patch: &mut Patch, length_unit: LengthUnit) {
+        patch.length1 = 0;
+        patch.length2 = 0;
+        for diff in &patch.diffs {
+            let len = length_unit.text_len(diff.text());
+            match diff {
+                Diff::Add(_) => patch.length2 += len,
+                Diff::Delete(_) => patch.length1 += len,
+                Diff::Keep(_) => {
+                    patch.length1 += len;
+                    patch.length2 += len;
+                }
+            }
+        }
+    }
+
     /// Given an Vector of patches, return another Vector that is identical.
     ///
     /// Args:
@@ -2453,6 +2971,25 @@ impl Dmp {
         &self,
         patches: &mut Vec<Patch>,
         source_text: &str,
+    ) -> (Vec<char>, Vec<bool>) {
+        self.patch_apply_unit(patches, source_text, LengthUnit::UnicodeScalar)
+    }
+
+    /// Merge a set of patches whose offsets and lengths are counted in
+    /// length_unit onto the text.
+    ///
+    /// Args:
+    ///     patches: Vector of Patch objects.
+    ///     text: Old text.
+    ///     length_unit: Unit of the patch offsets and lengths.
+    ///
+    /// Returns:
+    ///     Two element Vector, containing the new chars and an Vector of boolean values.
+    pub fn patch_apply_unit(
+        &self,
+        patches: &mut Vec<Patch>,
+        source_text: &str,
+        length_unit: LengthUnit,
     ) -> (Vec<char>, Vec<bool>) {
         if patches.is_empty() {
             return (source_text.chars().collect(), vec![]);
@@ -2467,7 +3004,7 @@ impl Dmp {
         text.extend(source_text.chars());
         text.extend(&null_padding);
 
-        self.patch_splitmax(&mut patches_copy);
+        self.patch_splitmax_unit(&mut patches_copy, length_unit);
 
         // delta keeps track of the offset between the expected and actual location
         // of the previous patch.  If there are patches expected at positions 10 and
@@ -2477,6 +3014,8 @@ impl Dmp {
         let mut results: Vec<bool> = vec![false; patches_copy.len()];
         for x in 0..patches_copy.len() {
             let expected_loc: i32 = patches_copy[x].start2 as i32 + delta;
+            // Matching works on chars.
+            let expected_index = length_unit.to_char_index(&text, expected_loc);
             let text1: Vec<char> = self
                 .diff_text1(&mut patches_copy[x].diffs)
                 .chars()
@@ -2489,12 +3028,12 @@ impl Dmp {
                 let first: String = (text[..]).iter().collect();
                 let second: String = text1[..self.match_maxbits].iter().collect();
                 let second1: String = text1[text1.len() - self.match_maxbits..].iter().collect();
-                start_loc = self.match_main(first.as_str(), second.as_str(), expected_loc);
+                start_loc = self.match_main(first.as_str(), second.as_str(), expected_index);
                 if start_loc != -1 {
                     end_loc = self.match_main(
                         first.as_str(),
                         second1.as_str(),
-                        expected_loc + (text1.len() - self.match_maxbits) as i32,
+                        expected_index + (text1.len() - self.match_maxbits) as i32,
                     );
                     if end_loc == -1 || start_loc >= end_loc {
                         // Can't find valid trailing context.  Drop this patch.
@@ -2504,7 +3043,7 @@ impl Dmp {
             } else {
                 let first: String = text[..].iter().collect();
                 let second: String = text1[..].iter().collect();
-                start_loc = self.match_main(first.as_str(), second.as_str(), expected_loc);
+                start_loc = self.match_main(first.as_str(), second.as_str(), expected_index);
             }
             if start_loc == -1 {
                 // No match found.  :(
@@ -2514,7 +3053,7 @@ impl Dmp {
             } else {
                 // Found a match.  :)
                 results[x] = true;
-                delta = start_loc - expected_loc;
+                delta = length_unit.to_unit_index(&text, start_loc) - expected_loc;
 
                 let mut end_index: usize;
                 if end_loc == -1 {
@@ -2565,7 +3104,7 @@ impl Dmp {
                                     // Deletion
                                     let temp3: String =
                                         text[..(start_loc + index2) as usize].iter().collect();
-                                    let diffs_text_len = txt.len();
+                                    let diffs_text_len = txt.chars().count();
                                     let temp4: String = text[(start_loc
                                         + self.diff_xindex(&diffs, index1 + diffs_text_len as i32))
                                         as usize..]
@@ -2576,7 +3115,7 @@ impl Dmp {
                                 }
                             }
                             if let Diff::Keep(txt) | Diff::Add(txt) = mod1 {
-                                index1 += txt.len() as i32;
+                                index1 += txt.chars().count() as i32;
                             }
                         }
                     }
@@ -2588,6 +3127,100 @@ impl Dmp {
         (text, results)
     }
 
+    /// Merge a set of patches with offsets and lengths in UTF-16 code units,
+    /// e.g. made by the JavaScript or Java ports, onto the text.
+    ///
+    /// Args:
+    ///     patches: Vector of Patch objects.
+    ///     text: Old text.
+    ///
+    /// Returns:
+    ///     Two element Vector, containing the new chars and an Vector of boolean values.
+    pub fn patch_apply_u16(&self, patches: &mut Vec<Patch>, text: &str) -> (Vec<char>, Vec<bool>) {
+        self.patch_apply_unit(patches, text, LengthUnit::UTF16)
+    }
+
+    /// Merge only the chosen patches onto the text.  Patches which are not
+    /// selected are skipped as if they had failed to apply, so the ones
+    /// after them, like those after patches which did fail, are still
+    /// looked for at the right place.
+    ///
+    /// Args:
+    ///     patches: Vector of Patch objects.
+    ///     mask: Which patches to apply, missing entries count as false.
+    ///     text: Old text.
+    ///
+    /// Returns:
+    ///     Two element tuple, containing the new chars and a Vector of boolean
+    ///     values, one per patch, telling which patches were applied.
+    pub fn patch_apply_selected(
+        &self,
+        patches: &[Patch],
+        mask: &[bool],
+        text: &str,
+    ) -> (Vec<char>, Vec<bool>) {
+        let mut text: Vec<char> = text.chars().collect();
+        let mut results: Vec<bool> = vec![false; patches.len()];
+        // Offset caused by the patches skipped so far.
+        let mut skipped: i32 = 0;
+        for (i, patch) in patches.iter().enumerate() {
+            if !mask.get(i).copied().unwrap_or(false) {
+                skipped += patch.length2 as i32 - patch.length1 as i32;
+                continue;
+            }
+            let mut shifted = patch.clone();
+            shifted.start2 = max(0, patch.start2 as i32 - skipped) as usize;
+            let source: String = text.iter().collect();
+            let (patched, applied) = self.patch_apply(&mut vec![shifted], &source);
+            text = patched;
+            results[i] = applied.iter().all(|applied| *applied);
+            if !results[i] {
+                // Failed patches leave the text as it was, like skipped ones.
+                skipped += patch.length2 as i32 - patch.length1 as i32;
+            }
+        }
+        (text, results)
+    }
+
+    /// Compute the patches which undo the given ones: insertions become
+    /// deletions and the other way round.  Applying the result to the new
+    /// text gives back the old one.  Patches are applied in order, each to
+    /// the text left by the ones before, so the starts of an inverted patch
+    /// are moved back by the net length change of the patches before it.
+    ///
+    /// Args:
+    ///     patches: Vector of Patch objects.
+    ///
+    /// Returns:
+    ///     Vector of inverted Patch objects, in the same order.
+    pub fn patch_invert(&self, patches: &[Patch]) -> Vec<Patch> {
+        // Net length change of the patches inverted so far.
+        let mut delta: i64 = 0;
+        patches
+            .iter()
+            .map(|patch| {
+                let mut diffs: Vec<Diff> = vec![];
+                let mut insertions: Vec<Diff> = vec![];
+                for diff in &patch.diffs {
+                    match diff {
+                        Diff::Add(txt) => diffs.push(Diff::Delete(txt.clone())),
+                        Diff::Delete(txt) => insertions.push(Diff::Add(txt.clone())),
+                        Diff::Keep(txt) => {
+                            // Keep deletions in front of insertions within an edit.
+                            diffs.append(&mut insertions);
+                            diffs.push(Diff::Keep(txt.clone()));
+                        }
+                    }
+                }
+                diffs.append(&mut insertions);
+                let start1 = max(0, patch.start2 as i64 - delta) as usize;
+                let start2 = max(0, patch.start1 as i64 - delta) as usize;
+                delta += patch.length2 as i64 - patch.length1 as i64;
+                Patch::new(diffs, start1, start2, patch.length2, patch.length1)
+            })
+            .collect()
+    }
+
     /// Add some padding on text start and end so that edges can match
     /// something.  Intended to be called only from within patch_apply.
     ///
@@ -2666,6 +3299,17 @@ impl Dmp {
     /// Args:
     ///     patches: Array of Patch objects.
     pub fn patch_splitmax(&self, patches: &mut Vec<Patch>) {
+        self.patch_splitmax_unit(patches, LengthUnit::UnicodeScalar)
+    }
+
+    /// Look through the patches and break up any which are longer than the
+    /// maximum limit of the match algorithm, with offsets and lengths counted
+    /// in length_unit.
+    ///
+    /// Args:
+    ///     patches: Array of Patch objects.
+    ///     length_unit: Unit of the patch offsets and lengths.
+    pub fn patch_splitmax_unit(&self, patches: &mut Vec<Patch>, length_unit: LengthUnit) {
         let patch_size = self.match_maxbits;
         if patch_size == 0 {
             return;
@@ -2686,11 +3330,12 @@ impl Dmp {
                 // Create one of several smaller patches.
                 let mut patch = Patch::new(vec![], 0, 0, 0, 0);
                 let mut empty = true;
-                patch.start1 = start1 - precontext.len();
-                patch.start2 = start2 - precontext.len();
+                let precontext_len = length_unit.chars_len(&precontext);
+                patch.start1 = start1 - precontext_len;
+                patch.start2 = start2 - precontext_len;
                 if !precontext.is_empty() {
-                    patch.length1 = precontext.len();
-                    patch.length2 = precontext.len();
+                    patch.length1 = precontext_len;
+                    patch.length2 = precontext_len;
                     patch
                         .diffs
                         .push(Diff::Keep(precontext.clone().iter().collect()));
@@ -2700,8 +3345,9 @@ impl Dmp {
                     match &bigpatch.diffs[0] {
                         Diff::Add(txt) => {
                             // Insertions are harmless.
-                            patch.length2 += txt.len();
-                            start2 += txt.len();
+                            let txt_len = length_unit.text_len(txt);
+                            patch.length2 += txt_len;
+                            start2 += txt_len;
                             patch.diffs.push(bigpatch.diffs[0].clone());
                             bigpatch.diffs.remove(0);
                             empty = false;
@@ -2709,29 +3355,35 @@ impl Dmp {
                         Diff::Delete(txt)
                             if patch.diffs.len() == 1
                                 && matches!(patch.diffs[0], Diff::Keep(_))
-                                && txt.len() > 2 * patch_size =>
+                                && length_unit.text_len(txt) > 2 * patch_size =>
                         {
                             // This is a large deletion.  Let it pass in one chunk.
-                            patch.length1 += txt.len();
-                            start1 += txt.len();
+                            let txt_len = length_unit.text_len(txt);
+                            patch.length1 += txt_len;
+                            start1 += txt_len;
                             empty = false;
                             patch.diffs.push(bigpatch.diffs[0].with_text(txt.clone()));
                             bigpatch.diffs.remove(0);
                         }
                         Diff::Keep(txt) | Diff::Delete(txt) => {
-                            // Deletion or equality.  Only take as much as we can stomach.
-                            let diff_text_len: i32 = txt.len() as i32;
-                            let diff_text: Vec<char> = txt.chars().collect();
-                            let diff_text = diff_text[..min(
-                                diff_text_len as usize,
-                                patch_size - patch.length1 - self.patch_margin,
-                            ) as usize]
-                                .to_vec();
-                            patch.length1 += diff_text.len();
-                            start1 += diff_text.len();
+                            // Deletion or equality.  Only take as much as we can stomach,
+                            // but at least one char.
+                            let budget = patch_size - patch.length1 - self.patch_margin;
+                            let mut diff_text: Vec<char> = vec![];
+                            let mut diff_text_len = 0;
+                            for ch in txt.chars() {
+                                let ch_len = length_unit.char_len(ch);
+                                if !diff_text.is_empty() && diff_text_len + ch_len > budget {
+                                    break;
+                                }
+                                diff_text.push(ch);
+                                diff_text_len += ch_len;
+                            }
+                            patch.length1 += diff_text_len;
+                            start1 += diff_text_len;
                             if let Diff::Keep(_) = bigpatch.diffs[0] {
-                                patch.length2 += diff_text.len();
-                                start2 += diff_text.len();
+                                patch.length2 += diff_text_len;
+                                start2 += diff_text_len;
                             } else {
                                 empty = false;
                             }
@@ -2763,10 +3415,10 @@ impl Dmp {
                 } else {
                     self.diff_text1(&mut bigpatch.diffs)
                 };
-                let postcontext_len = postcontext.chars().count() as i32;
+                let postcontext_len = length_unit.text_len(&postcontext);
                 if !postcontext.is_empty() {
-                    patch.length1 += postcontext_len as usize;
-                    patch.length2 += postcontext_len as usize;
+                    patch.length1 += postcontext_len;
+                    patch.length2 += postcontext_len;
                     if !patch.diffs.is_empty()
                         && matches!(patch.diffs[patch.diffs.len() - 1], Diff::Keep(_))
                     {
@@ -2786,6 +3438,12 @@ impl Dmp {
     }
 
     /// Take a list of patches and return a textual representation.
+    /// Ranges are written the way the other ports write them, which earlier
+    /// versions of this crate did not: a range of one char leaves out its
+    /// length ("@@ -1 +1 @@", not "@@ -1,1 +1,1 @@"), and an empty range
+    /// names the char before it with a length of 0 ("-5,0", not "-6").
+    /// patch_from_text still reads the ",1" spelling, but reads an old
+    /// empty range such as "-6" as one char, like the other ports do.
     ///
     /// Args:
     ///     patches: Vector of Patch objects.
@@ -2812,30 +3470,117 @@ impl Dmp {
     /// Raises:
     ///     ValueError: If invalid input.
     pub fn patch_from_text(&self, textline: String) -> Vec<Patch> {
-        let text: Vec<String> = textline.split("@@ ").map(|x| x.to_string()).collect();
+        self.patch_parse(&textline)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Parse a textual representation of patches whose offsets and lengths
+    /// are counted in length_unit.
+    ///
+    /// Args:
+    ///     textline: Text representation of patches.
+    ///     length_unit: Unit of the patch offsets and lengths.
+    ///
+    /// Returns:
+    ///     Vector of Patch objects.
+    pub fn patch_from_text_unit(&self, textline: String, length_unit: LengthUnit) -> Vec<Patch> {
+        self.patch_parse_unit(&textline, length_unit)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn patch1_from_text(&self, textline: String) -> Patch {
+        self.patch1_parse(&textline)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Take a list of patches with offsets and lengths in UTF-16 code units
+    /// and return the textual representation the JavaScript and Java ports
+    /// read.  The text is escaped the same way as by patch_to_text, only the
+    /// numbers differ from scalar patches, so this is patch_to_text for
+    /// patches from the _u16 functions.
+    ///
+    /// Args:
+    ///     patches: Vector of Patch objects.
+    ///
+    /// Returns:
+    ///     Text representation of patches.
+    pub fn patch_to_text_u16(&self, patches: &mut Vec<Patch>) -> String {
+        self.patch_to_text(patches)
+    }
+
+    /// Parse a textual representation of patches written by the JavaScript
+    /// or Java ports, whose offsets and lengths are in UTF-16 code units.
+    ///
+    /// Args:
+    ///     textline: Text representation of patches.
+    ///
+    /// Returns:
+    ///     Vector of Patch objects.
+    pub fn patch_from_text_u16(&self, textline: String) -> Vec<Patch> {
+        self.patch_from_text_unit(textline, LengthUnit::UTF16)
+    }
+
+    /// Parse a textual representation of patches, reporting malformed input
+    /// as an error instead of panicking.
+    ///
+    /// Args:
+    ///     textline: Text representation of patches.
+    ///
+    /// Returns:
+    ///     Vector of Patch objects or a ParseError.
+    pub fn patch_parse(&self, textline: &str) -> Result<Vec<Patch>, ParseError> {
+        self.patch_parse_unit(textline, LengthUnit::UnicodeScalar)
+    }
+
+    /// Parse a textual representation of patches whose offsets and lengths
+    /// are counted in length_unit, reporting malformed input as an error.
+    ///
+    /// Args:
+    ///     textline: Text representation of patches.
+    ///     length_unit: Unit of the patch offsets and lengths.
+    ///
+    /// Returns:
+    ///     Vector of Patch objects or a ParseError.
+    pub fn patch_parse_unit(
+        &self,
+        textline: &str,
+        length_unit: LengthUnit,
+    ) -> Result<Vec<Patch>, ParseError> {
+        let text: Vec<&str> = textline.split("@@ ").collect();
         let mut patches: Vec<Patch> = vec![];
         for (i, text_item) in text.iter().enumerate() {
             if text_item.is_empty() {
                 if i == 0 {
                     continue;
                 }
-                panic!("wrong patch string");
+                return Err(ParseError::new("wrong patch string"));
             }
-            patches.push(self.patch1_from_text(text_item.clone()));
+            let mut patch = self.patch1_parse(text_item)?;
+            self.patch_lengths_unit(&mut patch, length_unit);
+            patches.push(patch);
         }
-        patches
+        Ok(patches)
     }
 
-    pub fn patch1_from_text(&self, textline: String) -> Patch {
-        let text: Vec<String> = textline.split('\n').map(|x| x.to_string()).collect();
+    /// Parse the textual representation of one patch, without the leading
+    /// "@@ ", reporting malformed input as an error.
+    ///
+    /// Args:
+    ///     textline: Text representation of a patch.
+    ///
+    /// Returns:
+    ///     Patch object or a ParseError.
+    pub fn patch1_parse(&self, textline: &str) -> Result<Patch, ParseError> {
+        let text: Vec<&str> = textline.split('\n').collect();
         let mut text_vec: Vec<char> = text[0].chars().collect();
         if text_vec.len() < 8
             || text_vec[text_vec.len() - 1] != '@'
             || text_vec[text_vec.len() - 2] != '@'
         {
-            panic!("Invalid patch string");
+            return Err(ParseError::new("Invalid patch string"));
         }
-        let mut patch = Patch::new(vec![], 0, 0, 0, 0);
+        // A missing length means one char.
+        let mut patch = Patch::new(vec![], 0, 0, 1, 1);
         let mut i = 0;
         let mut temp: i32 = 0;
         while i < text_vec.len() {
@@ -2851,88 +3596,81 @@ impl Dmp {
                 s.push(text_vec[i]);
                 i += 1;
             }
+            let number = s
+                .parse::<usize>()
+                .map_err(|_| ParseError::new("Invalid patch string"))?;
             if temp == 0 {
-                patch.start1 = s.parse::<usize>().unwrap().saturating_sub(1);
+                patch.start1 = number;
                 temp += 1;
             } else if temp == 1 {
-                patch.length1 = s.parse().unwrap();
+                patch.length1 = number;
                 temp += 1;
             } else if temp == 2 {
-                patch.start2 = s.parse::<usize>().unwrap().saturating_sub(1);
+                patch.start2 = number;
                 temp += 1;
             } else if temp == 3 {
-                patch.length2 = s.parse().unwrap();
+                patch.length2 = number;
                 temp += 1;
             } else {
-                panic!("Invalid patch string");
+                return Err(ParseError::new("Invalid patch string"));
             }
             i += 1;
         }
+        // An empty range names the char before it, others their first char.
+        if patch.length1 != 0 {
+            patch.start1 = patch.start1.saturating_sub(1);
+        }
+        if patch.length2 != 0 {
+            patch.start2 = patch.start2.saturating_sub(1);
+        }
         patch.length1 = 0;
         patch.length2 = 0;
         for text_item in text.iter().take(text.len() - 1).skip(1) {
             text_vec = text_item.chars().collect();
+            if text_vec.is_empty() {
+                return Err(ParseError::new("wrong patch string"));
+            }
+            let temp6: String = text_vec[1..].iter().collect();
+            let temp6 = percent_decode(temp6.as_bytes())
+                .decode_utf8()
+                .map_err(|_| ParseError::new("wrong patch string"))?
+                .to_string();
             if text_vec[0] == '+' {
                 // Insertion.
-                let mut temp6: String = text_vec[1..].iter().collect();
-                temp6 = percent_decode(temp6.as_bytes())
-                    .decode_utf8()
-                    .unwrap()
-                    .to_string();
                 patch.length2 += temp6.chars().count();
                 patch.diffs.push(Diff::Add(temp6));
             } else if text_vec[0] == '-' {
                 // Deletion.
-                let mut temp6: String = text_vec[1..].iter().collect();
-                temp6 = percent_decode(temp6.as_bytes())
-                    .decode_utf8()
-                    .unwrap()
-                    .to_string();
                 patch.length1 += temp6.chars().count();
                 patch.diffs.push(Diff::Delete(temp6));
             } else if text_vec[0] == ' ' {
                 // Minor equality.
-                let mut temp6: String = text_vec[1..].iter().collect();
-                temp6 = percent_decode(temp6.as_bytes())
-                    .decode_utf8()
-                    .unwrap()
-                    .to_string();
                 patch.length1 += temp6.chars().count();
                 patch.length2 += temp6.chars().count();
                 patch.diffs.push(Diff::Keep(temp6));
             } else {
-                panic!("wrong patch string");
+                return Err(ParseError::new("wrong patch string"));
             }
         }
-        patch
+        Ok(patch)
     }
 }
 
 impl Display for Patch {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // Convert patch to string.
-        let mut text = "@@ -".to_string();
-        let mut start1: u32 = (self.start1 + 1) as u32;
-        if self.length1 == 0 && start1 == 1 {
-            start1 -= 1;
-        }
-        text += start1.to_string().as_str();
-        if self.length1 > 0 || start1 == 0 {
-            text += ",";
-            let length1: u32 = self.length1 as u32;
-            text += length1.to_string().as_str();
-        }
-        text += " +";
-        let mut start2: u32 = (self.start2 + 1) as u32;
-        if self.length2 == 0 && start2 == 1 {
-            start2 -= 1;
-        }
-        text += start2.to_string().as_str();
-        if self.length2 > 0 || start2 == 0 {
-            text += ",";
-            let length2: u32 = self.length2 as u32;
-            text += length2.to_string().as_str();
-        }
+        // Convert patch to string.  Ranges are written the way the other
+        // ports write them: an empty range names the char before it and a
+        // length of one is left out.
+        let coords = |start: usize, length: usize| match length {
+            0 => format!("{start},0"),
+            1 => (start + 1).to_string(),
+            _ => format!("{},{}", start + 1, length),
+        };
+        let mut text = format!(
+            "@@ -{} +{}",
+            coords(self.start1, self.length1),
+            coords(self.start2, self.length2)
+        );
         text += " @@\n";
         for i in 0..self.diffs.len() {
             let (ch, txt) = match &self.diffs[i] {
@@ -0,0 +1,285 @@
+/*C ABI for diff, patch make and patch apply.
+
+A Dmp is handed out as an opaque pointer from dmp_new and released with
+dmp_free.  Texts go in as UTF-8 pointer and length pairs; texts that come
+out are DmpString values, also NUL terminated for convenience, which the
+caller releases with the matching free function.  Functions which can fail
+return a DmpStatus and write their result through an out pointer; panics
+are caught and reported as DMP_STATUS_PANIC instead of unwinding into C.
+
+The header is include/rusty_diff_match_patch.h, generated by cbindgen with
+cbindgen.toml; tests/header.rs checks it is up to date.  The crate is only
+an rlib unless asked otherwise, so build the shared and static library in
+target/release with `make ffi`, which runs:
+
+    cargo rustc --lib --release --features ffi --crate-type cdylib,staticlib
+
+Linking the static library also needs -lpthread -ldl -lm on Linux.
+*/
+
+use std::ffi::c_char;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use std::time::Duration;
+
+use super::dmp::Dmp;
+
+/// Outcome of a call.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DmpStatus {
+    Ok = 0,
+    // A NULL pointer where one is not allowed, or text which is not UTF-8.
+    InvalidArgument = 1,
+    // Malformed patch text.
+    ParseError = 2,
+    // The library panicked, which is a bug.
+    Panic = 3,
+}
+
+/// Text owned by the library, NUL terminated; len excludes the NUL.
+#[repr(C)]
+pub struct DmpString {
+    pub ptr: *mut c_char,
+    pub len: usize,
+}
+
+/// One diff: -1 for a deletion, 0 for an equality, 1 for an insertion.
+#[repr(C)]
+pub struct DmpDiff {
+    pub op: i32,
+    pub text: DmpString,
+}
+
+#[repr(C)]
+pub struct DmpDiffs {
+    pub ptr: *mut DmpDiff,
+    pub len: usize,
+}
+
+/// The patched text and whether each patch applied.
+#[repr(C)]
+pub struct DmpApplyResult {
+    pub text: DmpString,
+    pub results: *mut bool,
+    pub len: usize,
+}
+
+fn string_to_c(text: &str) -> DmpString {
+    let mut bytes = Vec::with_capacity(text.len() + 1);
+    bytes.extend_from_slice(text.as_bytes());
+    bytes.push(0);
+    DmpString {
+        ptr: Box::into_raw(bytes.into_boxed_slice()) as *mut c_char,
+        len: text.len(),
+    }
+}
+
+fn slice_to_c<T>(items: Vec<T>) -> (*mut T, usize) {
+    let len = items.len();
+    (Box::into_raw(items.into_boxed_slice()) as *mut T, len)
+}
+
+/// # Safety
+///
+/// ptr and len must come from slice_to_c.
+unsafe fn slice_from_c<T>(ptr: *mut T, len: usize) -> Box<[T]> {
+    Box::from_raw(ptr::slice_from_raw_parts_mut(ptr, len))
+}
+
+/// # Safety
+///
+/// ptr must be NULL or point to len readable bytes.
+unsafe fn str_from_c<'a>(ptr: *const c_char, len: usize) -> Result<&'a str, DmpStatus> {
+    if ptr.is_null() {
+        return if len == 0 {
+            Ok("")
+        } else {
+            Err(DmpStatus::InvalidArgument)
+        };
+    }
+    std::str::from_utf8(slice::from_raw_parts(ptr as *const u8, len))
+        .map_err(|_| DmpStatus::InvalidArgument)
+}
+
+/// Run f, turning a panic into DmpStatus::Panic.
+fn guard(f: impl FnOnce() -> Result<(), DmpStatus>) -> DmpStatus {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => DmpStatus::Ok,
+        Ok(Err(status)) => status,
+        Err(_) => DmpStatus::Panic,
+    }
+}
+
+/// Create a Dmp with the default settings.  Release it with dmp_free.
+#[no_mangle]
+pub extern "C" fn dmp_new() -> *mut Dmp {
+    Box::into_raw(Box::default())
+}
+
+/// Release a Dmp.
+///
+/// # Safety
+///
+/// dmp must be NULL or come from dmp_new, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn dmp_free(dmp: *mut Dmp) {
+    if !dmp.is_null() {
+        drop(Box::from_raw(dmp));
+    }
+}
+
+/// Set how many seconds to map a diff before giving up, 0 for no limit.
+///
+/// # Safety
+///
+/// dmp must come from dmp_new.
+#[no_mangle]
+pub unsafe extern "C" fn dmp_set_diff_timeout(dmp: *mut Dmp, seconds: f64) {
+    if let Some(dmp) = dmp.as_mut() {
+        dmp.diff_timeout = if seconds > 0.0 {
+            Duration::try_from_secs_f64(seconds).ok()
+        } else {
+            None
+        };
+    }
+}
+
+/// Find the differences between two texts.  Release the diffs with
+/// dmp_diffs_free.
+///
+/// # Safety
+///
+/// dmp must come from dmp_new, the texts must point to len readable bytes
+/// and out must point to writable memory for one DmpDiffs.
+#[no_mangle]
+pub unsafe extern "C" fn dmp_diff_main(
+    dmp: *const Dmp,
+    text1: *const c_char,
+    text1_len: usize,
+    text2: *const c_char,
+    text2_len: usize,
+    checklines: bool,
+    out: *mut DmpDiffs,
+) -> DmpStatus {
+    guard(|| {
+        let dmp = dmp.as_ref().ok_or(DmpStatus::InvalidArgument)?;
+        let out = out.as_mut().ok_or(DmpStatus::InvalidArgument)?;
+        let text1 = str_from_c(text1, text1_len)?;
+        let text2 = str_from_c(text2, text2_len)?;
+        let diffs: Vec<DmpDiff> = dmp
+            .diff_main(text1, text2, checklines)
+            .iter()
+            .map(|diff| DmpDiff {
+                op: diff.op(),
+                text: string_to_c(diff.text()),
+            })
+            .collect();
+        let (ptr, len) = slice_to_c(diffs);
+        *out = DmpDiffs { ptr, len };
+        Ok(())
+    })
+}
+
+/// Release diffs from dmp_diff_main.
+///
+/// # Safety
+///
+/// diffs must come from dmp_diff_main and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn dmp_diffs_free(diffs: DmpDiffs) {
+    if diffs.ptr.is_null() {
+        return;
+    }
+    for diff in slice_from_c(diffs.ptr, diffs.len).into_vec() {
+        dmp_string_free(diff.text);
+    }
+}
+
+/// Compute the patches turning text1 into text2, as patch text.  Release
+/// the text with dmp_string_free.
+///
+/// # Safety
+///
+/// dmp must come from dmp_new, the texts must point to len readable bytes
+/// and out must point to writable memory for one DmpString.
+#[no_mangle]
+pub unsafe extern "C" fn dmp_patch_make(
+    dmp: *const Dmp,
+    text1: *const c_char,
+    text1_len: usize,
+    text2: *const c_char,
+    text2_len: usize,
+    out: *mut DmpString,
+) -> DmpStatus {
+    guard(|| {
+        let dmp = dmp.as_ref().ok_or(DmpStatus::InvalidArgument)?;
+        let out = out.as_mut().ok_or(DmpStatus::InvalidArgument)?;
+        let text1 = str_from_c(text1, text1_len)?;
+        let text2 = str_from_c(text2, text2_len)?;
+        let mut patches = dmp.patch_make1(text1, text2);
+        *out = string_to_c(&dmp.patch_to_text(&mut patches));
+        Ok(())
+    })
+}
+
+/// Merge patch text onto a text.  Release the result with
+/// dmp_apply_result_free.
+///
+/// # Safety
+///
+/// dmp must come from dmp_new, the texts must point to len readable bytes
+/// and out must point to writable memory for one DmpApplyResult.
+#[no_mangle]
+pub unsafe extern "C" fn dmp_patch_apply(
+    dmp: *const Dmp,
+    patch_text: *const c_char,
+    patch_text_len: usize,
+    text: *const c_char,
+    text_len: usize,
+    out: *mut DmpApplyResult,
+) -> DmpStatus {
+    guard(|| {
+        let dmp = dmp.as_ref().ok_or(DmpStatus::InvalidArgument)?;
+        let out = out.as_mut().ok_or(DmpStatus::InvalidArgument)?;
+        let patch_text = str_from_c(patch_text, patch_text_len)?;
+        let text = str_from_c(text, text_len)?;
+        let mut patches = dmp
+            .patch_parse(patch_text)
+            .map_err(|_| DmpStatus::ParseError)?;
+        let (text, results) = dmp.patch_apply(&mut patches, text);
+        let (results, len) = slice_to_c(results);
+        *out = DmpApplyResult {
+            text: string_to_c(&text.into_iter().collect::<String>()),
+            results,
+            len,
+        };
+        Ok(())
+    })
+}
+
+/// Release a result from dmp_patch_apply.
+///
+/// # Safety
+///
+/// result must come from dmp_patch_apply and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn dmp_apply_result_free(result: DmpApplyResult) {
+    dmp_string_free(result.text);
+    if !result.results.is_null() {
+        drop(slice_from_c(result.results, result.len));
+    }
+}
+
+/// Release text returned by the library.
+///
+/// # Safety
+///
+/// text must come from this library and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn dmp_string_free(text: DmpString) {
+    if !text.ptr.is_null() {
+        drop(slice_from_c(text.ptr as *mut u8, text.len + 1));
+    }
+}
@@ -0,0 +1,382 @@
+/*Revision history of a document.
+
+Storing every revision in full wastes space, storing only deltas makes old
+revisions slow to rebuild.  A RevisionStore keeps a full snapshot every
+snapshot_interval revisions and the diff_todelta of the diffs from the
+previous revision in between, so rebuilding any revision takes at most
+snapshot_interval - 1 deltas.  A delta longer than the text it encodes is
+stored as a snapshot instead.
+
+Revisions are numbered from 0 and kept in a RevisionStorage: MemoryStorage
+or FsStorage, which writes one file per revision:
+
+    snapshot
+    <text>
+
+or
+
+    delta
+    <diff_todelta output>
+*/
+
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::dmp::{Diff, Dmp, LengthUnit, ParseError};
+
+/// How one revision is stored.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Revision {
+    // The full text.
+    Snapshot(String),
+    // Delta from the previous revision.
+    Delta(String),
+}
+
+/// Where a RevisionStore keeps its revisions.
+pub trait RevisionStorage {
+    /// Number of revisions stored.
+    fn revisions(&self) -> io::Result<u64>;
+
+    fn load(&self, revision: u64) -> io::Result<Revision>;
+
+    /// Store a revision, replacing the one with the same number.  Only
+    /// called with revision <= revisions().
+    fn store(&mut self, revision: u64, record: &Revision) -> io::Result<()>;
+}
+
+/// Revisions in memory.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStorage {
+    records: Vec<Revision>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> MemoryStorage {
+        MemoryStorage::default()
+    }
+}
+
+impl RevisionStorage for MemoryStorage {
+    fn revisions(&self) -> io::Result<u64> {
+        Ok(self.records.len() as u64)
+    }
+
+    fn load(&self, revision: u64) -> io::Result<Revision> {
+        self.records
+            .get(revision as usize)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such revision"))
+    }
+
+    fn store(&mut self, revision: u64, record: &Revision) -> io::Result<()> {
+        match self.records.get_mut(revision as usize) {
+            Some(stored) => *stored = record.clone(),
+            None => self.records.push(record.clone()),
+        }
+        Ok(())
+    }
+}
+
+/// Revisions as files named <revision>.rev in a directory.
+#[derive(Debug, Clone)]
+pub struct FsStorage {
+    dir: PathBuf,
+}
+
+impl FsStorage {
+    /// Use dir, creating it if needed.
+    pub fn open(dir: &Path) -> io::Result<FsStorage> {
+        fs::create_dir_all(dir)?;
+        Ok(FsStorage {
+            dir: dir.to_path_buf(),
+        })
+    }
+
+    fn path(&self, revision: u64) -> PathBuf {
+        self.dir.join(format!("{revision}.rev"))
+    }
+}
+
+impl RevisionStorage for FsStorage {
+    fn revisions(&self) -> io::Result<u64> {
+        let mut revisions = 0;
+        for entry in fs::read_dir(&self.dir)? {
+            let name = entry?.file_name();
+            let revision = name
+                .to_str()
+                .and_then(|name| name.strip_suffix(".rev"))
+                .and_then(|stem| stem.parse::<u64>().ok());
+            if let Some(revision) = revision {
+                revisions = revisions.max(revision + 1);
+            }
+        }
+        Ok(revisions)
+    }
+
+    fn load(&self, revision: u64) -> io::Result<Revision> {
+        let text = fs::read_to_string(self.path(revision))?;
+        match text.split_once('\n') {
+            Some(("snapshot", text)) => Ok(Revision::Snapshot(text.to_string())),
+            Some(("delta", delta)) => Ok(Revision::Delta(delta.to_string())),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("revision {revision} is neither a snapshot nor a delta"),
+            )),
+        }
+    }
+
+    fn store(&mut self, revision: u64, record: &Revision) -> io::Result<()> {
+        let contents = match record {
+            Revision::Snapshot(text) => format!("snapshot\n{text}"),
+            Revision::Delta(delta) => format!("delta\n{delta}"),
+        };
+        // Write aside and rename, so a crash never leaves half a revision.
+        let path = self.path(revision);
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, contents)?;
+        fs::rename(temp, path)
+    }
+}
+
+/// Why a revision could not be stored or rebuilt.
+#[derive(Debug)]
+pub enum HistoryError {
+    Io(io::Error),
+    // A stored delta does not fit the revision before it.
+    Parse(ParseError),
+    // The revision has not been committed.
+    NotFound(u64),
+}
+
+impl Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HistoryError::Io(err) => write!(f, "{err}"),
+            HistoryError::Parse(err) => write!(f, "{err}"),
+            HistoryError::NotFound(revision) => write!(f, "no revision {revision}"),
+        }
+    }
+}
+
+impl Error for HistoryError {}
+
+impl From<io::Error> for HistoryError {
+    fn from(err: io::Error) -> Self {
+        HistoryError::Io(err)
+    }
+}
+
+impl From<ParseError> for HistoryError {
+    fn from(err: ParseError) -> Self {
+        HistoryError::Parse(err)
+    }
+}
+
+/// The revisions of one document.
+pub struct RevisionStore<S: RevisionStorage> {
+    storage: S,
+    dmp: Dmp,
+    // Longest run of revisions rebuilt from one snapshot.
+    snapshot_interval: u64,
+    // Latest revision, its text and the number of deltas since a snapshot.
+    tip: Option<(u64, String, u64)>,
+}
+
+impl<S: RevisionStorage> RevisionStore<S> {
+    /// Args:
+    ///     storage: Where the revisions are, possibly from an earlier run.
+    ///     snapshot_interval: Take a snapshot at least every this many
+    ///         revisions, 1 to store every revision in full.
+    pub fn new(storage: S, snapshot_interval: u64) -> RevisionStore<S> {
+        RevisionStore {
+            storage,
+            dmp: Dmp::default(),
+            snapshot_interval: snapshot_interval.max(1),
+            tip: None,
+        }
+    }
+
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    pub fn revisions(&self) -> Result<u64, HistoryError> {
+        Ok(self.storage.revisions()?)
+    }
+
+    /// Pick the record for a revision following one with text1, given the
+    /// number of deltas since the last snapshot.
+    fn record(&self, text1: Option<&str>, text2: &str, chain: u64) -> Revision {
+        match text1 {
+            Some(text1) if chain + 1 < self.snapshot_interval => {
+                let mut diffs = self.dmp.diff_main(text1, text2, true);
+                self.dmp.diff_cleanup_efficiency(&mut diffs);
+                let delta = self.dmp.diff_todelta(&mut diffs);
+                if delta.len() < text2.len() {
+                    Revision::Delta(delta)
+                } else {
+                    Revision::Snapshot(text2.to_string())
+                }
+            }
+            _ => Revision::Snapshot(text2.to_string()),
+        }
+    }
+
+    /// Store text as the next revision.
+    ///
+    /// Returns:
+    ///     Number of the new revision.
+    pub fn commit(&mut self, text: &str) -> Result<u64, HistoryError> {
+        let revisions = self.storage.revisions()?;
+        let tip = match self.tip.take() {
+            Some(tip) if tip.0 + 1 == revisions => Some(tip),
+            _ if revisions == 0 => None,
+            _ => Some(self.rebuild(revisions - 1, None)?),
+        };
+        let record = match &tip {
+            Some((_, text1, chain)) => self.record(Some(text1), text, *chain),
+            None => self.record(None, text, 0),
+        };
+        let chain = match (&record, &tip) {
+            (Revision::Delta(_), Some((_, _, chain))) => chain + 1,
+            _ => 0,
+        };
+        self.storage.store(revisions, &record)?;
+        self.tip = Some((revisions, text.to_string(), chain));
+        Ok(revisions)
+    }
+
+    /// Rebuild a revision from the nearest snapshot before it, or from base
+    /// if that is closer.
+    ///
+    /// Returns:
+    ///     The revision, its text and the number of deltas since a snapshot.
+    fn rebuild(
+        &self,
+        revision: u64,
+        base: Option<(u64, &str)>,
+    ) -> Result<(u64, String, u64), HistoryError> {
+        let mut deltas: Vec<String> = vec![];
+        let mut text = loop {
+            let current = revision - deltas.len() as u64;
+            if let Some((base_revision, base_text)) = base {
+                if base_revision == current {
+                    break base_text.to_string();
+                }
+            }
+            match self.storage.load(current)? {
+                Revision::Snapshot(text) => break text,
+                Revision::Delta(delta) if current > 0 => deltas.push(delta),
+                Revision::Delta(_) => {
+                    return Err(ParseError::new("revision 0 is not a snapshot").into())
+                }
+            }
+        };
+        let chain = deltas.len() as u64;
+        for delta in deltas.iter().rev() {
+            let mut diffs = self
+                .dmp
+                .diff_parse_delta(&text, delta, LengthUnit::UnicodeScalar)?;
+            text = self.dmp.diff_text2(&mut diffs);
+        }
+        Ok((revision, text, chain))
+    }
+
+    fn check(&self, revision: u64) -> Result<(), HistoryError> {
+        if revision >= self.storage.revisions()? {
+            return Err(HistoryError::NotFound(revision));
+        }
+        Ok(())
+    }
+
+    /// Rebuild the text of a revision.
+    pub fn checkout(&self, revision: u64) -> Result<String, HistoryError> {
+        if let Some((tip, text, _)) = &self.tip {
+            if *tip == revision {
+                return Ok(text.clone());
+            }
+        }
+        self.check(revision)?;
+        Ok(self.rebuild(revision, None)?.1)
+    }
+
+    /// Find the differences between two revisions.  The later revision is
+    /// rebuilt from the earlier one where that is closer than a snapshot,
+    /// and consecutive revisions come straight from the stored delta.
+    ///
+    /// Args:
+    ///     revision1: Old revision.
+    ///     revision2: New revision.
+    ///
+    /// Returns:
+    ///     Vector of diffs as changes.
+    pub fn diff(&self, revision1: u64, revision2: u64) -> Result<Vec<Diff>, HistoryError> {
+        let (early, late) = (revision1.min(revision2), revision1.max(revision2));
+        self.check(late)?;
+        let early_text = self.checkout(early)?;
+        if late == early + 1 {
+            if let Revision::Delta(delta) = self.storage.load(late)? {
+                let diffs =
+                    self.dmp
+                        .diff_parse_delta(&early_text, &delta, LengthUnit::UnicodeScalar)?;
+                if revision1 < revision2 {
+                    return Ok(diffs);
+                }
+                // The same edits, undone.
+                return Ok(diffs
+                    .into_iter()
+                    .map(|diff| match diff {
+                        Diff::Add(text) => Diff::Delete(text),
+                        Diff::Delete(text) => Diff::Add(text),
+                        keep => keep,
+                    })
+                    .collect());
+            }
+        }
+        let late_text = self.rebuild(late, Some((early, &early_text)))?.1;
+        Ok(if revision1 <= revision2 {
+            self.dmp.diff_main(&early_text, &late_text, true)
+        } else {
+            self.dmp.diff_main(&late_text, &early_text, true)
+        })
+    }
+
+    /// Rewrite the stored revisions so they follow the current
+    /// snapshot_interval: snapshots where a chain would grow too long, or a
+    /// delta would not be shorter than the text, and deltas everywhere
+    /// else.  Only revisions whose record changes are written.
+    ///
+    /// Args:
+    ///     snapshot_interval: New interval, see new.
+    ///
+    /// Returns:
+    ///     Number of revisions rewritten.
+    pub fn compact(&mut self, snapshot_interval: u64) -> Result<u64, HistoryError> {
+        self.snapshot_interval = snapshot_interval.max(1);
+        self.tip = None;
+        let mut previous: Option<String> = None;
+        let mut chain = 0;
+        let mut rewritten = 0;
+        for revision in 0..self.storage.revisions()? {
+            let text = match &previous {
+                Some(text) => self.rebuild(revision, Some((revision - 1, text)))?.1,
+                None => self.rebuild(revision, None)?.1,
+            };
+            let record = self.record(previous.as_deref(), &text, chain);
+            chain = match record {
+                Revision::Delta(_) => chain + 1,
+                Revision::Snapshot(_) => 0,
+            };
+            if self.storage.load(revision)? != record {
+                self.storage.store(revision, &record)?;
+                rewritten += 1;
+            }
+            previous = Some(text);
+        }
+        Ok(rewritten)
+    }
+}
@@ -0,0 +1,140 @@
+/*Hunks of a line-mode diff.
+
+Changed lines are grouped together with a number of unchanged context lines
+around them, hunks whose context would overlap are merged into one.
+*/
+
+use std::fmt::{self, Display};
+
+use super::dmp::{Diff, Dmp};
+
+/// A run of changed lines with its context.  Start lines count from 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hunk {
+    // One diff per line.
+    pub diffs: Vec<Diff>,
+    pub start1: usize,
+    pub start2: usize,
+    pub length1: usize,
+    pub length2: usize,
+}
+
+impl Hunk {
+    /// Iterate over all lines of the hunk, context included.
+    pub fn lines(&self) -> std::slice::Iter<'_, Diff> {
+        self.diffs.iter()
+    }
+
+    /// Iterate over the lines of the hunk as they were in the old text.
+    pub fn old_lines(&self) -> impl Iterator<Item = &str> {
+        self.diffs.iter().filter_map(|diff| match diff {
+            Diff::Keep(txt) | Diff::Delete(txt) => Some(txt.as_str()),
+            Diff::Add(_) => None,
+        })
+    }
+
+    /// Iterate over the lines of the hunk as they are in the new text.
+    pub fn new_lines(&self) -> impl Iterator<Item = &str> {
+        self.diffs.iter().filter_map(|diff| match diff {
+            Diff::Keep(txt) | Diff::Add(txt) => Some(txt.as_str()),
+            Diff::Delete(_) => None,
+        })
+    }
+}
+
+impl Display for Hunk {
+    /// Format the hunk the way unified diff output does.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let range = |start: usize, length: usize| match length {
+            // An empty range names the line before it.
+            0 => format!("{},0", start - 1),
+            1 => start.to_string(),
+            _ => format!("{start},{length}"),
+        };
+        writeln!(
+            f,
+            "@@ -{} +{} @@",
+            range(self.start1, self.length1),
+            range(self.start2, self.length2)
+        )?;
+        for diff in &self.diffs {
+            let (ch, txt) = match diff {
+                Diff::Keep(txt) => (' ', txt),
+                Diff::Delete(txt) => ('-', txt),
+                Diff::Add(txt) => ('+', txt),
+            };
+            write!(f, "{ch}{txt}")?;
+            if !txt.ends_with('\n') {
+                write!(f, "\n\\ No newline at end of file\n")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Dmp {
+    /// Group a line-mode diff into hunks of changed lines surrounded by up
+    /// to context_lines unchanged lines.  Hunks which are separated by no
+    /// more than twice the context are merged.
+    ///
+    /// Args:
+    ///     diffs: Vector of diffs made of whole lines, e.g. from diff_lines.
+    ///     context_lines: Number of unchanged lines to keep around changes.
+    ///
+    /// Returns:
+    ///     Vector of hunks.
+    pub fn diff_hunks(&self, diffs: &[Diff], context_lines: usize) -> Vec<Hunk> {
+        let lines = self.diff_split_lines(diffs);
+
+        // Number of lines before each line, in the old and the new text.
+        let mut positions: Vec<(usize, usize)> = vec![];
+        let mut line1 = 0;
+        let mut line2 = 0;
+        for line in &lines {
+            positions.push((line1, line2));
+            match line {
+                Diff::Keep(_) => {
+                    line1 += 1;
+                    line2 += 1;
+                }
+                Diff::Delete(_) => line1 += 1,
+                Diff::Add(_) => line2 += 1,
+            }
+        }
+        let changes: Vec<usize> = (0..lines.len())
+            .filter(|i| !matches!(lines[*i], Diff::Keep(_)))
+            .collect();
+
+        let mut hunks: Vec<Hunk> = vec![];
+        let mut i = 0;
+        while i < changes.len() {
+            let first = changes[i].saturating_sub(context_lines);
+            let mut last = changes[i];
+            while i + 1 < changes.len() && changes[i + 1] - last - 1 <= 2 * context_lines {
+                i += 1;
+                last = changes[i];
+            }
+            let last = (last + context_lines + 1).min(lines.len());
+
+            let diffs = lines[first..last].to_vec();
+            let length1 = diffs
+                .iter()
+                .filter(|diff| !matches!(diff, Diff::Add(_)))
+                .count();
+            let length2 = diffs
+                .iter()
+                .filter(|diff| !matches!(diff, Diff::Delete(_)))
+                .count();
+            let (start1, start2) = positions[first];
+            hunks.push(Hunk {
+                diffs,
+                start1: start1 + 1,
+                start2: start2 + 1,
+                length1,
+                length2,
+            });
+            i += 1;
+        }
+        hunks
+    }
+}
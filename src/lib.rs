@@ -1,4 +1,43 @@
+#[cfg(feature = "async")]
+mod async_diff;
+mod blame;
+mod builder;
+mod dirs;
 mod dmp;
+#[cfg(feature = "ffi")]
+mod ffi;
+mod history;
+mod hunks;
+mod moves;
+mod normalize;
+mod patch_set;
 mod percent_encoding;
+#[cfg(feature = "python")]
+mod python;
+mod scoring;
+mod segment;
+mod side_by_side;
+mod similarity;
+mod stats;
+mod sync;
+#[cfg(feature = "wasm")]
+mod wasm;
 
+pub use blame::*;
+pub use builder::*;
+pub use dirs::*;
 pub use dmp::*;
+#[cfg(feature = "ffi")]
+pub use ffi::*;
+pub use history::*;
+pub use hunks::*;
+pub use moves::*;
+pub use normalize::*;
+pub use patch_set::*;
+pub use scoring::*;
+pub use side_by_side::*;
+pub use similarity::*;
+pub use stats::*;
+pub use sync::*;
+#[cfg(feature = "wasm")]
+pub use wasm::*;
@@ -0,0 +1,145 @@
+/*Move detection.
+
+A moved block of text shows up in a diff as a deletion in one place and an
+insertion of the same (or nearly the same) text in another.  This pass pairs
+them up so they can be presented as moves.
+*/
+
+use super::dmp::{Diff, Dmp};
+
+/// A block of text deleted at one diff and inserted at another.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Move {
+    // Index of the deletion within the diffs.
+    pub from: usize,
+    // Index of the insertion within the diffs.
+    pub to: usize,
+    // The moved text, as it was deleted.
+    pub text: String,
+    // True if the inserted text is identical to the deleted one.
+    pub exact: bool,
+}
+
+impl Dmp {
+    /// Find blocks of text which were deleted in one place and inserted in
+    /// another.  Exact copies are paired for all deletions first, then the
+    /// remaining insertions which contain the start of a deletion (located
+    /// with match_bitap) and differ from it by no more than match_threshold.
+    /// A deletion and an insertion of the same edit are a replacement, not
+    /// a move, and are never paired.
+    ///
+    /// Args:
+    ///     diffs: Vector of diff object, typically after diff_cleanup_semantic.
+    ///     min_length: Shortest deletion (in chars) worth reporting as a move.
+    ///
+    /// Returns:
+    ///     Vector of moves, ordered by deletion.
+    pub fn diff_moves(&self, diffs: &[Diff], min_length: usize) -> Vec<Move> {
+        // Number every edit region so replacements can be told apart.
+        let mut regions: Vec<usize> = vec![];
+        let mut region = 0;
+        for diff in diffs {
+            if let Diff::Keep(_) = diff {
+                region += 1;
+            }
+            regions.push(region);
+        }
+
+        // Only deletions long enough to be reported can be moves.
+        let deletions: Vec<usize> = (0..diffs.len())
+            .filter(|from| match &diffs[*from] {
+                Diff::Delete(deleted) => deleted.chars().count() >= min_length,
+                _ => false,
+            })
+            .collect();
+        let candidates = |from: usize, used: &[bool]| -> Vec<usize> {
+            (0..diffs.len())
+                .filter(|to| {
+                    !used[*to]
+                        && regions[*to] != regions[from]
+                        && matches!(diffs[*to], Diff::Add(_))
+                })
+                .collect()
+        };
+
+        // Pair exact copies first, so that a fuzzy match of an earlier
+        // deletion cannot take the insertion a later deletion is identical to.
+        let mut moves: Vec<Move> = vec![];
+        let mut used = vec![false; diffs.len()];
+        let mut paired = vec![false; diffs.len()];
+        for from in &deletions {
+            let deleted = diffs[*from].text();
+            let exact = candidates(*from, &used)
+                .into_iter()
+                .find(|to| diffs[*to].text() == deleted);
+            if let Some(to) = exact {
+                used[to] = true;
+                paired[*from] = true;
+                moves.push(Move {
+                    from: *from,
+                    to,
+                    text: deleted.clone(),
+                    exact: true,
+                });
+            }
+        }
+        for from in &deletions {
+            if paired[*from] {
+                continue;
+            }
+            let deleted = diffs[*from].text();
+            let candidates = candidates(*from, &used);
+            if let Some((to, exact)) = self.diff_moves_fuzzy(deleted, diffs, &candidates) {
+                used[to] = true;
+                moves.push(Move {
+                    from: *from,
+                    to,
+                    text: deleted.clone(),
+                    exact,
+                });
+            }
+        }
+        moves.sort_by_key(|found| found.from);
+        moves
+    }
+
+    /// Pick the insertion most similar to a deleted text.
+    ///
+    /// Args:
+    ///     deleted: Deleted text.
+    ///     diffs: Vector of diff object.
+    ///     candidates: Indices of the insertions to consider.
+    ///
+    /// Returns:
+    ///     Index of the best insertion and false, or None if none is close enough.
+    fn diff_moves_fuzzy(
+        &self,
+        deleted: &str,
+        diffs: &[Diff],
+        candidates: &[usize],
+    ) -> Option<(usize, bool)> {
+        let deleted_vec: Vec<char> = deleted.chars().collect();
+        let pattern_len = if self.match_maxbits == 0 {
+            deleted_vec.len()
+        } else {
+            deleted_vec.len().min(self.match_maxbits)
+        };
+        let pattern = &deleted_vec[..pattern_len];
+
+        let mut best: Option<(usize, f32)> = None;
+        for to in candidates {
+            let inserted = diffs[*to].text();
+            let inserted_vec: Vec<char> = inserted.chars().collect();
+            if self.match_bitap(&inserted_vec, pattern, 0) == -1 {
+                continue;
+            }
+            let changes = self.diff_main(deleted, inserted, false);
+            let longest = deleted_vec.len().max(inserted_vec.len());
+            let score = self.diff_levenshtein(&changes) as f32 / longest as f32;
+            if score <= self.match_threshold && best.is_none_or(|(_, best)| score < best) {
+                best = Some((*to, score));
+            }
+        }
+        best.map(|(to, _)| (to, false))
+    }
+}
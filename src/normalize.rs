@@ -0,0 +1,450 @@
+/*Normalized comparison for diff and match.
+
+Text is split into tokens which are compared by a normalized key while the
+original spelling is kept around, so the diffs handed back to the caller
+still carry the original text.
+*/
+
+use std::collections::HashMap;
+
+use caseless::default_case_fold_str;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::dmp::{Deadline, Diff, Dmp};
+
+/// Unicode normalization form applied to text before it is compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Normalization {
+    #[default]
+    None,
+    NFC,
+    NFKC,
+}
+
+/// Whitespace differences to ignore when comparing lines, after the
+/// `--ignore-space-at-eol`, `-b` and `-w` options of diff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IgnoreSpace {
+    #[default]
+    None,
+    // Whitespace at the end of a line.
+    AtEol,
+    // Changes in the amount of whitespace, including whitespace at the end of a line.
+    Change,
+    // All whitespace inside a line.
+    All,
+}
+
+/// A piece of text compared by its key but reported with its original text.
+/// Ignored tokens take no part in the comparison at all.
+pub(crate) struct Token<'a> {
+    pub key: String,
+    pub text: &'a str,
+    pub ignored: bool,
+}
+
+impl Dmp {
+    /// Is any of the comparison options (case, normalization, whitespace) set?
+    pub(crate) fn compare_normalized(&self) -> bool {
+        self.ignore_case || self.ignore_whitespace || self.normalization != Normalization::None
+    }
+
+    /// Is any of the line-only comparison options (ignore_space, ignore_blank_lines) set?
+    pub(crate) fn compare_lines_normalized(&self) -> bool {
+        self.ignore_space != IgnoreSpace::None || self.ignore_blank_lines
+    }
+
+    /// Compute the key under which a text is compared, honouring
+    /// normalization and ignore_case.  Case is compared by full case
+    /// folding, so "ß" matches "SS" and "ς" matches "Σ".
+    ///
+    /// Args:
+    ///     text: Text to normalize.
+    ///
+    /// Returns:
+    ///     Normalized text.
+    pub fn normalize_text(&self, text: &str) -> String {
+        let normalized = self.normalize_form(text);
+        if self.ignore_case {
+            // Folding may decompose a char, so normalize once more.
+            self.normalize_form(&default_case_fold_str(&normalized))
+        } else {
+            normalized
+        }
+    }
+
+    fn normalize_form(&self, text: &str) -> String {
+        match self.normalization {
+            Normalization::None => text.to_string(),
+            Normalization::NFC => text.nfc().collect(),
+            Normalization::NFKC => text.nfkc().collect(),
+        }
+    }
+
+    /// Compute the key under which a line is compared, honouring
+    /// normalization, ignore_case and ignore_space.  The line terminator
+    /// is kept so a missing newline at the end of a text still shows.
+    ///
+    /// Args:
+    ///     line: Line to normalize, with or without its terminator.
+    ///
+    /// Returns:
+    ///     Normalized line.
+    pub fn normalize_line(&self, line: &str) -> String {
+        let ignore_space = if self.ignore_whitespace {
+            IgnoreSpace::All
+        } else {
+            self.ignore_space
+        };
+        if ignore_space == IgnoreSpace::None {
+            return self.normalize_text(line);
+        }
+        let (body, newline) = match line.strip_suffix('\n') {
+            Some(body) => (body, "\n"),
+            None => (line, ""),
+        };
+        let body = body.trim_end();
+        let mut key = match ignore_space {
+            IgnoreSpace::None | IgnoreSpace::AtEol => body.to_string(),
+            IgnoreSpace::Change => body
+                .split(char::is_whitespace)
+                .filter(|word| !word.is_empty())
+                .collect::<Vec<_>>()
+                .join(" "),
+            IgnoreSpace::All => body.chars().filter(|ch| !ch.is_whitespace()).collect(),
+        };
+        // A leading run of whitespace still counts as one space for -b.
+        if ignore_space == IgnoreSpace::Change && body.starts_with(char::is_whitespace) {
+            key.insert(0, ' ');
+        }
+        key += newline;
+        self.normalize_text(&key)
+    }
+
+    /// Split a text into extended grapheme clusters, so that canonically
+    /// equivalent sequences, including composed and decomposed Hangul
+    /// syllables, produce equal keys.
+    ///
+    /// Args:
+    ///     text: Text to split.
+    ///
+    /// Returns:
+    ///     Vector of tokens covering the whole text.
+    fn diff_tokenize<'a>(&self, text: &'a str) -> Vec<Token<'a>> {
+        text.graphemes(true)
+            .map(|grapheme| self.make_token(grapheme))
+            .collect()
+    }
+
+    fn make_token<'a>(&self, text: &'a str) -> Token<'a> {
+        let ignored = self.ignore_whitespace && text.chars().all(char::is_whitespace);
+        Token {
+            key: self.normalize_text(text),
+            text,
+            ignored,
+        }
+    }
+
+    /// Find the differences between two texts, comparing them with the
+    /// case, normalization and whitespace options of this object.  A run
+    /// of changes between two equalities is reported as one deletion
+    /// followed by one insertion.  Where normalize_text gives the same
+    /// key for both, only the spelling changed, otherwise it is an edit.
+    ///
+    /// Args:
+    ///     text1: Old text to be diffed.
+    ///     text2: New text to be diffed.
+    ///
+    /// Returns:
+    ///     Vector of diffs as changes.
+    pub(crate) fn diff_main_normalized(
+        &self,
+        text1: &str,
+        text2: &str,
+        deadline: Deadline<'_>,
+    ) -> Vec<Diff> {
+        let tokens1 = self.diff_tokenize(text1);
+        let tokens2 = self.diff_tokenize(text2);
+        join_changes(self.diff_tokens(&tokens1, &tokens2, deadline))
+    }
+
+    /// Find the line-by-line differences between two texts, comparing lines
+    /// with the line comparison options of this object.
+    ///
+    /// Args:
+    ///     text1: Old text to be diffed.
+    ///     text2: New text to be diffed.
+    ///
+    /// Returns:
+    ///     Vector of diffs made of whole lines.
+    pub(crate) fn diff_lines_internal(
+        &self,
+        text1: &str,
+        text2: &str,
+        deadline: Deadline<'_>,
+    ) -> Vec<Diff> {
+        let tokens1 = self.line_tokenize(text1);
+        let tokens2 = self.line_tokenize(text2);
+        self.diff_tokens(&tokens1, &tokens2, deadline)
+    }
+
+    /// Find the line-by-line differences between two texts like
+    /// diff_lines, but without the text: lines which compare equal may be
+    /// spelled differently.  Blank lines are never ignored.
+    ///
+    /// Returns:
+    ///     Vector of diffs with one char per line.
+    pub(crate) fn diff_line_keys(&self, text1: &str, text2: &str) -> Vec<Diff> {
+        let dmp = Dmp {
+            ignore_blank_lines: false,
+            ..self.clone()
+        };
+        let tokens1 = dmp.line_tokenize(text1);
+        let tokens2 = dmp.line_tokenize(text2);
+        dmp.diff_keys(&tokens1, &tokens2, Deadline::now())
+    }
+
+    fn line_tokenize<'a>(&self, text: &'a str) -> Vec<Token<'a>> {
+        text.split_inclusive('\n')
+            .map(|line| Token {
+                key: self.normalize_line(line),
+                text: line,
+                ignored: self.ignore_blank_lines && line.trim().is_empty(),
+            })
+            .collect()
+    }
+
+    /// A copy of this object which compares text exactly, for diffing
+    /// keys that are already normalized.
+    fn exact(&self) -> Dmp {
+        Dmp {
+            ignore_case: false,
+            normalization: Normalization::None,
+            ignore_whitespace: false,
+            ignore_space: IgnoreSpace::None,
+            ignore_blank_lines: false,
+            ..self.clone()
+        }
+    }
+
+    /// Diff two token sequences by key, leaving out ignored tokens.
+    ///
+    /// Returns:
+    ///     Vector of diffs with one char per compared token.
+    fn diff_keys(&self, tokens1: &[Token], tokens2: &[Token], deadline: Deadline<'_>) -> Vec<Diff> {
+        let mut codes: HashMap<&str, u32> = HashMap::new();
+        let chars1 = encode_tokens(tokens1, &mut codes);
+        let chars2 = encode_tokens(tokens2, &mut codes);
+        self.exact()
+            .diff_main_internal(&chars1, &chars2, false, deadline)
+    }
+
+    /// Diff two token sequences by key and rehydrate the result with the
+    /// original token text.  Tokens which compare equal are only kept as
+    /// equalities where both sides spell them the same, otherwise they are
+    /// replaced, so diff_text1 and diff_text2 give back the two texts.
+    /// Ignored tokens are compared the same way, as they appear between
+    /// the compared ones.
+    ///
+    /// Args:
+    ///     tokens1: Old tokens.
+    ///     tokens2: New tokens.
+    ///
+    /// Returns:
+    ///     Vector of diffs as changes.
+    pub(crate) fn diff_tokens(
+        &self,
+        tokens1: &[Token],
+        tokens2: &[Token],
+        deadline: Deadline<'_>,
+    ) -> Vec<Diff> {
+        let encoded = self.diff_keys(tokens1, tokens2, deadline);
+
+        let mut pointer1 = 0;
+        let mut pointer2 = 0;
+        let mut diffs: Vec<Diff> = vec![];
+        for diff in &encoded {
+            let count = diff.text().chars().count();
+            // Ignored tokens in front of a diff compare equal.
+            push_pair(
+                &mut diffs,
+                skip_ignored(tokens1, &mut pointer1),
+                skip_ignored(tokens2, &mut pointer2),
+            );
+            match diff {
+                Diff::Keep(_) => {
+                    for _ in 0..count {
+                        push_pair(
+                            &mut diffs,
+                            skip_ignored(tokens1, &mut pointer1),
+                            skip_ignored(tokens2, &mut pointer2),
+                        );
+                        push_pair(
+                            &mut diffs,
+                            take_tokens(tokens1, &mut pointer1, 1),
+                            take_tokens(tokens2, &mut pointer2, 1),
+                        );
+                    }
+                }
+                Diff::Delete(_) => {
+                    diffs.push(Diff::Delete(take_tokens(tokens1, &mut pointer1, count)));
+                }
+                Diff::Add(_) => {
+                    diffs.push(Diff::Add(take_tokens(tokens2, &mut pointer2, count)));
+                }
+            }
+        }
+        // Trailing ignored tokens.
+        push_pair(
+            &mut diffs,
+            skip_ignored(tokens1, &mut pointer1),
+            skip_ignored(tokens2, &mut pointer2),
+        );
+
+        // Join neighbours of the same kind, token boundaries must not shift.
+        let mut merged: Vec<Diff> = vec![];
+        for diff in diffs {
+            match merged.last_mut() {
+                Some(last) if std::mem::discriminant(last) == std::mem::discriminant(&diff) => {
+                    last.append_text(diff.text());
+                }
+                _ => merged.push(diff),
+            }
+        }
+        merged
+    }
+
+    /// Locate the best instance of 'pattern' in 'text' near 'loc', comparing
+    /// them with the case, normalization and whitespace options of this object.
+    ///
+    /// Args:
+    ///     text: The text to search.
+    ///     pattern: The pattern to search for.
+    ///     loc: The location to search around.
+    ///
+    /// Returns:
+    ///     Best match index (in chars of the original text) or -1.
+    pub(crate) fn match_main_normalized(&self, text: &str, pattern: &str, loc: i32) -> i32 {
+        // Map every normalized char back to the start of its token.
+        let mut normalized = "".to_string();
+        let mut starts: Vec<usize> = vec![];
+        let mut char_count = 0;
+        for token in self.diff_tokenize(text) {
+            if !token.ignored {
+                for ch in token.key.chars() {
+                    normalized.push(ch);
+                    starts.push(char_count);
+                }
+            }
+            char_count += token.text.chars().count();
+        }
+        let pattern: String = self
+            .diff_tokenize(pattern)
+            .iter()
+            .filter(|token| !token.ignored)
+            .map(|token| token.key.as_str())
+            .collect();
+
+        let loc = starts
+            .iter()
+            .position(|start| *start as i32 >= loc)
+            .unwrap_or(starts.len());
+        let found = self.match_main_internal(&normalized, &pattern, loc as i32);
+        if found < 0 {
+            return -1;
+        }
+        match starts.get(found as usize) {
+            Some(start) => *start as i32,
+            None => char_count as i32,
+        }
+    }
+}
+
+/// Reduce tokens to a string where each char represents one unique key.
+/// Keys already seen are looked up in codes, new ones are added to it.
+fn encode_tokens<'a>(tokens: &'a [Token], codes: &mut HashMap<&'a str, u32>) -> String {
+    let mut chars = "".to_string();
+    for token in tokens.iter().filter(|token| !token.ignored) {
+        let next = codes.len() as u32;
+        let code = *codes.entry(token.key.as_str()).or_insert(next);
+        // skip reserved range - U+D800 to U+DFFF
+        let code = if code >= 0xD800 { code + 0x800 } else { code };
+        chars.push(char::from_u32(code).unwrap_or(char::MAX));
+    }
+    chars
+}
+
+/// Step over ignored tokens and return their concatenated text.
+fn skip_ignored(tokens: &[Token], pointer: &mut usize) -> String {
+    let mut text = "".to_string();
+    while *pointer < tokens.len() && tokens[*pointer].ignored {
+        text += tokens[*pointer].text;
+        *pointer += 1;
+    }
+    text
+}
+
+/// Concatenate the text of the next count compared tokens, together with any
+/// ignored tokens between them.
+fn take_tokens(tokens: &[Token], pointer: &mut usize, count: usize) -> String {
+    let mut text = "".to_string();
+    let mut taken = 0;
+    while taken < count && *pointer < tokens.len() {
+        let token = &tokens[*pointer];
+        text += token.text;
+        if !token.ignored {
+            taken += 1;
+        }
+        *pointer += 1;
+    }
+    text
+}
+
+/// Push old and new text of tokens which compare equal: an equality when
+/// they are spelled the same, a replacement otherwise.
+fn push_pair(diffs: &mut Vec<Diff>, text1: String, text2: String) {
+    if text1 == text2 {
+        if !text1.is_empty() {
+            diffs.push(Diff::Keep(text1));
+        }
+        return;
+    }
+    if !text1.is_empty() {
+        diffs.push(Diff::Delete(text1));
+    }
+    if !text2.is_empty() {
+        diffs.push(Diff::Add(text2));
+    }
+}
+
+/// Join each run of changes between two equalities into one deletion and
+/// one insertion.  Unlike diff_cleanup_merge no text is moved between
+/// them, so token boundaries stay put.
+fn join_changes(diffs: Vec<Diff>) -> Vec<Diff> {
+    let mut joined: Vec<Diff> = vec![];
+    let mut text_delete = "".to_string();
+    let mut text_insert = "".to_string();
+    for diff in diffs {
+        match diff {
+            Diff::Delete(text) => text_delete += &text,
+            Diff::Add(text) => text_insert += &text,
+            Diff::Keep(text) => {
+                push_changes(&mut joined, &mut text_delete, &mut text_insert);
+                joined.push(Diff::Keep(text));
+            }
+        }
+    }
+    push_changes(&mut joined, &mut text_delete, &mut text_insert);
+    joined
+}
+
+/// Push the pending deletion and insertion of a run of changes and clear them.
+fn push_changes(diffs: &mut Vec<Diff>, text_delete: &mut String, text_insert: &mut String) {
+    if !text_delete.is_empty() {
+        diffs.push(Diff::Delete(std::mem::take(text_delete)));
+    }
+    if !text_insert.is_empty() {
+        diffs.push(Diff::Add(std::mem::take(text_insert)));
+    }
+}
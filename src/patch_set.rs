@@ -0,0 +1,277 @@
+/*Multi-file patch container.
+
+A patch set bundles the patches of several files together with what is
+needed to apply them safely: old and new path, file modes, a hash of the
+text the patches were made against, its encoding and the unit the patch
+offsets are counted in.  The body of every file is plain patch_to_text
+output:
+
+    dmp-patch-set 1
+    file
+    old-path src/a.txt
+    new-path src/a.txt
+    base-hash sha1:7b502c3a1f48c8609ae212cdfb639dee39673f5e
+    encoding utf-8
+    length-unit scalar
+    @@ -1,8 +1,8 @@
+    ...
+
+A missing old-path marks an added file, a missing new-path a deleted one.
+*/
+
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use url::percent_encoding::percent_decode;
+
+use super::dmp::{Dmp, LengthUnit, ParseError, Patch};
+
+const HEADER: &str = "dmp-patch-set";
+const VERSION: &str = "1";
+
+/// Patches of one file together with their metadata.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilePatch {
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+    pub old_mode: Option<u32>,
+    pub new_mode: Option<u32>,
+    // Hash of the text the patches apply to, see text_hash.
+    pub base_hash: Option<String>,
+    pub encoding: String,
+    pub length_unit: LengthUnit,
+    pub patches: Vec<Patch>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PatchSet {
+    pub files: Vec<FilePatch>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchSetError {
+    // The container or one of its patches is malformed.
+    Parse(ParseError),
+    // The text does not hash to the base-hash of the file.
+    HashMismatch { expected: String, actual: String },
+}
+
+impl Display for PatchSetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchSetError::Parse(err) => write!(f, "{err}"),
+            PatchSetError::HashMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "base text hash mismatch: expected {expected}, got {actual}"
+                )
+            }
+        }
+    }
+}
+
+impl Error for PatchSetError {}
+
+impl From<ParseError> for PatchSetError {
+    fn from(err: ParseError) -> Self {
+        PatchSetError::Parse(err)
+    }
+}
+
+/// Compute the hash recorded as base-hash: the SHA-1 of the UTF-8 bytes of
+/// the text, prefixed by the name of the algorithm.
+pub fn text_hash(text: &str) -> String {
+    format!("sha1:{}", sha1_smol::Sha1::from(text).digest())
+}
+
+impl FilePatch {
+    /// Wrap patches made against base_text, recording its hash.
+    pub fn new(
+        old_path: Option<String>,
+        new_path: Option<String>,
+        base_text: &str,
+        patches: Vec<Patch>,
+    ) -> FilePatch {
+        FilePatch {
+            old_path,
+            new_path,
+            old_mode: None,
+            new_mode: None,
+            base_hash: Some(text_hash(base_text)),
+            encoding: "utf-8".to_string(),
+            length_unit: LengthUnit::UnicodeScalar,
+            patches,
+        }
+    }
+}
+
+fn unit_name(unit: LengthUnit) -> &'static str {
+    match unit {
+        LengthUnit::UnicodeScalar => "scalar",
+        LengthUnit::UTF16 => "utf-16",
+        LengthUnit::UTF8 => "utf-8",
+    }
+}
+
+fn unit_from_name(name: &str) -> Option<LengthUnit> {
+    match name {
+        "scalar" => Some(LengthUnit::UnicodeScalar),
+        "utf-16" => Some(LengthUnit::UTF16),
+        "utf-8" => Some(LengthUnit::UTF8),
+        _ => None,
+    }
+}
+
+/// Escape the characters which would break a header line.
+fn encode_path(path: &str) -> String {
+    path.replace('%', "%25")
+        .replace('\n', "%0A")
+        .replace('\r', "%0D")
+}
+
+fn decode_path(path: &str) -> Result<String, ParseError> {
+    percent_decode(path.as_bytes())
+        .decode_utf8()
+        .map(|path| path.to_string())
+        .map_err(|_| ParseError::new("malformed path"))
+}
+
+impl Dmp {
+    /// Take a patch set and return its textual representation.
+    ///
+    /// Args:
+    ///     patch_set: PatchSet object.
+    ///
+    /// Returns:
+    ///     Text representation of the patch set.
+    pub fn patch_set_to_text(&self, patch_set: &PatchSet) -> String {
+        let mut text = format!("{HEADER} {VERSION}\n");
+        for file in &patch_set.files {
+            text += "file\n";
+            if let Some(path) = &file.old_path {
+                text += &format!("old-path {}\n", encode_path(path));
+            }
+            if let Some(path) = &file.new_path {
+                text += &format!("new-path {}\n", encode_path(path));
+            }
+            if let Some(mode) = file.old_mode {
+                text += &format!("old-mode {mode:o}\n");
+            }
+            if let Some(mode) = file.new_mode {
+                text += &format!("new-mode {mode:o}\n");
+            }
+            if let Some(hash) = &file.base_hash {
+                text += &format!("base-hash {hash}\n");
+            }
+            text += &format!("encoding {}\n", file.encoding);
+            text += &format!("length-unit {}\n", unit_name(file.length_unit));
+            for patch in &file.patches {
+                text += &patch.to_string();
+            }
+        }
+        text
+    }
+
+    /// Parse the textual representation of a patch set.
+    ///
+    /// Args:
+    ///     text: Text representation of a patch set.
+    ///
+    /// Returns:
+    ///     PatchSet object, or an error if the text is malformed.
+    pub fn patch_set_from_text(&self, text: &str) -> Result<PatchSet, PatchSetError> {
+        let mut lines = text.split('\n').peekable();
+        match lines.next().and_then(|line| line.split_once(' ')) {
+            Some((HEADER, VERSION)) => {}
+            Some((HEADER, _)) => {
+                return Err(ParseError::new("unsupported patch set version").into())
+            }
+            _ => return Err(ParseError::new("missing patch set header").into()),
+        }
+
+        let mut patch_set = PatchSet::default();
+        while let Some(line) = lines.next() {
+            if line.is_empty() && lines.peek().is_none() {
+                break;
+            }
+            if line != "file" {
+                return Err(ParseError::new("expected file section").into());
+            }
+            let mut file = FilePatch {
+                old_path: None,
+                new_path: None,
+                old_mode: None,
+                new_mode: None,
+                base_hash: None,
+                encoding: "utf-8".to_string(),
+                length_unit: LengthUnit::UnicodeScalar,
+                patches: vec![],
+            };
+
+            // Headers run up to the first patch.
+            while let Some(line) = lines
+                .next_if(|line| !line.is_empty() && *line != "file" && !line.starts_with("@@ "))
+            {
+                let Some((key, value)) = line.split_once(' ') else {
+                    return Err(ParseError::new("malformed header").into());
+                };
+                let mode = || {
+                    u32::from_str_radix(value, 8)
+                        .map_err(|_| ParseError::new("malformed file mode"))
+                };
+                match key {
+                    "old-path" => file.old_path = Some(decode_path(value)?),
+                    "new-path" => file.new_path = Some(decode_path(value)?),
+                    "old-mode" => file.old_mode = Some(mode()?),
+                    "new-mode" => file.new_mode = Some(mode()?),
+                    "base-hash" => file.base_hash = Some(value.to_string()),
+                    "encoding" => file.encoding = value.to_string(),
+                    "length-unit" => {
+                        file.length_unit = unit_from_name(value)
+                            .ok_or_else(|| ParseError::new("unknown length unit"))?;
+                    }
+                    _ => return Err(ParseError::new("unknown header").into()),
+                }
+            }
+
+            // The body is everything up to the next file.
+            let mut body = "".to_string();
+            while let Some(line) = lines.next_if(|line| !line.is_empty() && *line != "file") {
+                body += line;
+                body += "\n";
+            }
+            file.patches = self.patch_parse_unit(&body, file.length_unit)?;
+            patch_set.files.push(file);
+        }
+        Ok(patch_set)
+    }
+
+    /// Merge the patches of one file onto its text, after checking that
+    /// the text is the one the patches were made against.
+    ///
+    /// Args:
+    ///     file: FilePatch object.
+    ///     text: Old text of the file.
+    ///
+    /// Returns:
+    ///     Two element tuple, containing the new text and a Vector of boolean
+    ///     values, or an error if the text does not match base-hash.
+    pub fn patch_set_apply(
+        &self,
+        file: &FilePatch,
+        text: &str,
+    ) -> Result<(String, Vec<bool>), PatchSetError> {
+        if let Some(expected) = &file.base_hash {
+            let actual = text_hash(text);
+            if *expected != actual {
+                return Err(PatchSetError::HashMismatch {
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+        }
+        let mut patches = file.patches.clone();
+        let (text, results) = self.patch_apply_unit(&mut patches, text, file.length_unit);
+        Ok((text.iter().collect(), results))
+    }
+}
@@ -13,19 +13,13 @@ pub fn percent_decode_u16(input: &[u8]) -> Result<Vec<u16>, Box<dyn std::error::
     let mut input_iter = input.iter();
     let mut result: Vec<u16> = Vec::new();
 
-    while let Some(&byte) = input_iter.next() {
-        if byte != b'%' {
-            result.push(byte as u16);
-            continue;
-        }
-
-        let byte1 = next_percent_encoded_byte(&mut input_iter, true);
+    while let Some(byte1) = next_byte(&mut input_iter)? {
         if (byte1 & 0x80) == 0 {
             result.push(byte1);
             continue;
         }
 
-        let mut byte2 = next_percent_encoded_byte(&mut input_iter, false);
+        let mut byte2 = next_byte(&mut input_iter)?.ok_or(DecodeError)?;
         // continuation bytes have bitmask 10xx xxxx
         if (byte2 & 0xC0) != 0x80 {
             return Err(DecodeError.into());
@@ -44,7 +38,7 @@ pub fn percent_decode_u16(input: &[u8]) -> Result<Vec<u16>, Box<dyn std::error::
             continue;
         }
 
-        let mut byte3 = next_percent_encoded_byte(&mut input_iter, false);
+        let mut byte3 = next_byte(&mut input_iter)?.ok_or(DecodeError)?;
         if (byte3 & 0xC0) != 0x80 {
             return Err(DecodeError.into());
         }
@@ -61,7 +55,7 @@ pub fn percent_decode_u16(input: &[u8]) -> Result<Vec<u16>, Box<dyn std::error::
             continue;
         }
 
-        let mut byte4 = next_percent_encoded_byte(&mut input_iter, false);
+        let mut byte4 = next_byte(&mut input_iter)?.ok_or(DecodeError)?;
         if (byte4 & 0xC0) != 0x80 {
             return Err(DecodeError.into());
         }
@@ -79,7 +73,7 @@ pub fn percent_decode_u16(input: &[u8]) -> Result<Vec<u16>, Box<dyn std::error::
                 | ((byte2 as u32) << 0x0C)
                 | ((byte3 as u32) << 0x06)
                 | byte4 as u32;
-            if (0x010000..0x10FFFF).contains(&code_point) {
+            if (0x010000..=0x10FFFF).contains(&code_point) {
                 code_point -= 0x010000;
 
                 result.push((((code_point >> 10) & 0x3FF) | 0xD800) as u16);
@@ -94,15 +88,23 @@ pub fn percent_decode_u16(input: &[u8]) -> Result<Vec<u16>, Box<dyn std::error::
     Ok(result)
 }
 
-fn next_percent_encoded_byte(iter: &mut std::slice::Iter<u8>, skip_percent: bool) -> u16 {
-    if !skip_percent && iter.next() != Some(&b'%') {
-        panic!("URI malformed");
+/// Read one byte, either percent encoded or as is.  Bytes which are not
+/// percent encoded are part of the UTF-8 of the text.
+fn next_byte(iter: &mut std::slice::Iter<u8>) -> Result<Option<u16>, DecodeError> {
+    match iter.next() {
+        None => Ok(None),
+        Some(&b'%') => {
+            let mut digit = || {
+                iter.next()
+                    .and_then(|&b| (b as char).to_digit(16))
+                    .ok_or(DecodeError)
+            };
+            let h = digit()?;
+            let l = digit()?;
+            Ok(Some((h * 0x10 + l) as u16))
+        }
+        Some(&byte) => Ok(Some(byte as u16)),
     }
-
-    let h = iter.next().and_then(|&b| (b as char).to_digit(16)).unwrap();
-    let l = iter.next().and_then(|&b| (b as char).to_digit(16)).unwrap();
-
-    (h as u8 * 0x10 + l as u8) as u16
 }
 
 #[cfg(test)]
@@ -126,4 +128,19 @@ mod tests {
         let result = percent_decode_u16("123%ED%B5%B1".as_bytes());
         assert_eq!(result.unwrap(), vec![49, 50, 51, 56689]);
     }
+
+    #[test]
+    fn test_decode_raw_utf8() {
+        let result = percent_decode_u16("é🅱".as_bytes());
+        assert_eq!(result.unwrap(), vec![0xE9, 55356, 56689]);
+    }
+
+    #[test]
+    fn test_decode_malformed() {
+        assert!(percent_decode_u16("%".as_bytes()).is_err());
+        assert!(percent_decode_u16("%4".as_bytes()).is_err());
+        assert!(percent_decode_u16("%zz".as_bytes()).is_err());
+        assert!(percent_decode_u16("%F0%9F".as_bytes()).is_err());
+        assert!(percent_decode_u16("%C3A".as_bytes()).is_err());
+    }
 }
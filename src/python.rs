@@ -0,0 +1,408 @@
+/*Python bindings with the API of the Python port.
+
+The module exports the diff_match_patch and patch_obj classes with the
+method and attribute names of the pure Python package, diffs are
+(op, text) tuples, so code using its public API switches by changing the
+import:
+
+    from rusty_diff_match_patch import diff_match_patch
+
+Offsets count code points like Python strings do.  Long diffs run without
+holding the GIL.  Build with `maturin build`, which builds the cdylib
+itself, see pyproject.toml.  tests/python.rs runs tests/python/test_drop_in.py
+against the module with `cargo test --features python`.
+
+It is no drop-in replacement for code calling the helpers the Python port
+uses internally and tests on their own, which are not exported: diff_compute, diff_lineMode, diff_bisect, diff_bisectSplit,
+diff_linesToChars, diff_charsToLines, diff_commonPrefix, diff_commonSuffix,
+diff_commonOverlap, diff_halfMatch, match_bitap, match_alphabet,
+patch_addContext, patch_deepCopy, patch_addPadding and patch_splitMax.
+*/
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyList, PyString};
+
+use super::dmp::{Diff, Dmp, Patch};
+
+type PyDiff = (i32, String);
+
+fn diff_to_py(diff: &Diff) -> PyDiff {
+    (diff.op(), diff.text().clone())
+}
+
+fn diffs_to_py(diffs: &[Diff]) -> Vec<PyDiff> {
+    diffs.iter().map(diff_to_py).collect()
+}
+
+/// Read (op, text) pairs from any sequence of two element sequences.
+fn diffs_from_py(diffs: &Bound<'_, PyAny>) -> PyResult<Vec<Diff>> {
+    diffs
+        .try_iter()?
+        .map(|diff| {
+            let diff = diff?;
+            let op: i32 = diff.get_item(0)?.extract()?;
+            let text: String = diff.get_item(1)?.extract()?;
+            Diff::from_op(op, text).ok_or_else(|| PyValueError::new_err("unknown diff operation"))
+        })
+        .collect()
+}
+
+/// A patch, with the fields of patch_obj of the Python port.
+#[pyclass(name = "patch_obj", module = "rusty_diff_match_patch")]
+pub struct PyPatch {
+    // Kept as the list itself, so changing it in place changes the patch.
+    #[pyo3(get)]
+    diffs: Py<PyList>,
+    #[pyo3(get, set)]
+    start1: usize,
+    #[pyo3(get, set)]
+    start2: usize,
+    #[pyo3(get, set)]
+    length1: usize,
+    #[pyo3(get, set)]
+    length2: usize,
+}
+
+impl PyPatch {
+    fn from_patch(py: Python<'_>, patch: &Patch) -> PyResult<PyPatch> {
+        Ok(PyPatch {
+            diffs: PyList::new(py, diffs_to_py(&patch.diffs))?.unbind(),
+            start1: patch.start1,
+            start2: patch.start2,
+            length1: patch.length1,
+            length2: patch.length2,
+        })
+    }
+
+    fn to_patch(&self, py: Python<'_>) -> PyResult<Patch> {
+        Ok(Patch::new(
+            diffs_from_py(self.diffs.bind(py))?,
+            self.start1,
+            self.start2,
+            self.length1,
+            self.length2,
+        ))
+    }
+}
+
+#[pymethods]
+impl PyPatch {
+    #[new]
+    fn new(py: Python<'_>) -> PyResult<PyPatch> {
+        PyPatch::from_patch(py, &Patch::new(vec![], 0, 0, 0, 0))
+    }
+
+    /// Any sequence of (op, text) pairs; a list is kept as it is.
+    #[setter]
+    fn set_diffs(&mut self, diffs: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.diffs = match diffs.cast::<PyList>() {
+            Ok(list) => list.clone().unbind(),
+            Err(_) => {
+                PyList::new(diffs.py(), diffs.try_iter()?.collect::<PyResult<Vec<_>>>()?)?.unbind()
+            }
+        };
+        Ok(())
+    }
+
+    fn __str__(&self, py: Python<'_>) -> PyResult<String> {
+        Ok(self.to_patch(py)?.to_string())
+    }
+}
+
+fn patches_to_py(py: Python<'_>, patches: &[Patch]) -> PyResult<Vec<Py<PyPatch>>> {
+    patches
+        .iter()
+        .map(|patch| Py::new(py, PyPatch::from_patch(py, patch)?))
+        .collect()
+}
+
+fn patches_from_py(patches: &Bound<'_, PyAny>) -> PyResult<Vec<Patch>> {
+    patches
+        .try_iter()?
+        .map(|patch| {
+            patch?
+                .extract::<PyRef<'_, PyPatch>>()?
+                .to_patch(patches.py())
+        })
+        .collect()
+}
+
+/// The diff_match_patch class of the Python port.
+#[pyclass(name = "diff_match_patch", module = "rusty_diff_match_patch")]
+pub struct PyDiffMatchPatch {
+    dmp: Dmp,
+}
+
+#[pymethods]
+impl PyDiffMatchPatch {
+    #[classattr]
+    const DIFF_DELETE: i32 = -1;
+    #[classattr]
+    const DIFF_INSERT: i32 = 1;
+    #[classattr]
+    const DIFF_EQUAL: i32 = 0;
+
+    #[new]
+    fn new() -> PyDiffMatchPatch {
+        // The defaults of the Python port.
+        PyDiffMatchPatch {
+            dmp: Dmp {
+                diff_timeout: Some(Duration::from_secs(1)),
+                edit_cost: 4,
+                ..Dmp::default()
+            },
+        }
+    }
+
+    /// Number of seconds to map a diff before giving up (0 for infinity).
+    #[getter(Diff_Timeout)]
+    fn diff_timeout(&self) -> f64 {
+        self.dmp
+            .diff_timeout
+            .map_or(0.0, |timeout| timeout.as_secs_f64())
+    }
+
+    #[setter(Diff_Timeout)]
+    fn set_diff_timeout(&mut self, seconds: f64) {
+        self.dmp.diff_timeout = if seconds > 0.0 {
+            Duration::try_from_secs_f64(seconds).ok()
+        } else {
+            None
+        };
+    }
+
+    #[getter(Diff_EditCost)]
+    fn edit_cost(&self) -> usize {
+        self.dmp.edit_cost
+    }
+
+    #[setter(Diff_EditCost)]
+    fn set_edit_cost(&mut self, edit_cost: usize) {
+        self.dmp.edit_cost = edit_cost;
+    }
+
+    #[getter(Match_Threshold)]
+    fn match_threshold(&self) -> f32 {
+        self.dmp.match_threshold
+    }
+
+    #[setter(Match_Threshold)]
+    fn set_match_threshold(&mut self, match_threshold: f32) {
+        self.dmp.match_threshold = match_threshold;
+    }
+
+    #[getter(Match_Distance)]
+    fn match_distance(&self) -> usize {
+        self.dmp.match_distance
+    }
+
+    #[setter(Match_Distance)]
+    fn set_match_distance(&mut self, match_distance: usize) {
+        self.dmp.match_distance = match_distance;
+    }
+
+    #[getter(Patch_DeleteThreshold)]
+    fn patch_delete_threshold(&self) -> f32 {
+        self.dmp.patch_delete_threshold
+    }
+
+    #[setter(Patch_DeleteThreshold)]
+    fn set_patch_delete_threshold(&mut self, patch_delete_threshold: f32) {
+        self.dmp.patch_delete_threshold = patch_delete_threshold;
+    }
+
+    #[getter(Patch_Margin)]
+    fn patch_margin(&self) -> usize {
+        self.dmp.patch_margin
+    }
+
+    #[setter(Patch_Margin)]
+    fn set_patch_margin(&mut self, patch_margin: usize) {
+        self.dmp.patch_margin = patch_margin;
+    }
+
+    #[getter(Match_MaxBits)]
+    fn match_maxbits(&self) -> usize {
+        self.dmp.match_maxbits
+    }
+
+    #[setter(Match_MaxBits)]
+    fn set_match_maxbits(&mut self, match_maxbits: usize) {
+        self.dmp.match_maxbits = match_maxbits;
+    }
+
+    /// Find the differences between two texts, as (op, text) tuples.  Like
+    /// in the Python port, a deadline in seconds since the epoch, as given
+    /// by time.time(), takes the place of Diff_Timeout.
+    #[pyo3(signature = (text1, text2, checklines=true, deadline=None))]
+    fn diff_main(
+        &self,
+        py: Python<'_>,
+        text1: &str,
+        text2: &str,
+        checklines: bool,
+        deadline: Option<f64>,
+    ) -> Vec<PyDiff> {
+        let dmp = match deadline {
+            Some(deadline) => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0.0, |now| now.as_secs_f64());
+                Dmp {
+                    diff_timeout: Some(
+                        Duration::try_from_secs_f64(deadline - now).unwrap_or(Duration::ZERO),
+                    ),
+                    ..self.dmp.clone()
+                }
+            }
+            None => self.dmp.clone(),
+        };
+        let diffs = py.detach(|| dmp.diff_main(text1, text2, checklines));
+        diffs_to_py(&diffs)
+    }
+
+    #[pyo3(name = "diff_cleanupSemantic")]
+    fn diff_cleanup_semantic(&self, diffs: &Bound<'_, PyList>) -> PyResult<()> {
+        self.cleanup(diffs, Dmp::diff_cleanup_semantic)
+    }
+
+    #[pyo3(name = "diff_cleanupSemanticLossless")]
+    fn diff_cleanup_semantic_lossless(&self, diffs: &Bound<'_, PyList>) -> PyResult<()> {
+        self.cleanup(diffs, Dmp::diff_cleanup_semantic_lossless)
+    }
+
+    #[pyo3(name = "diff_cleanupEfficiency")]
+    fn diff_cleanup_efficiency(&self, diffs: &Bound<'_, PyList>) -> PyResult<()> {
+        self.cleanup(diffs, Dmp::diff_cleanup_efficiency)
+    }
+
+    #[pyo3(name = "diff_cleanupMerge")]
+    fn diff_cleanup_merge(&self, diffs: &Bound<'_, PyList>) -> PyResult<()> {
+        self.cleanup(diffs, Dmp::diff_cleanup_merge)
+    }
+
+    #[pyo3(name = "diff_xIndex")]
+    fn diff_xindex(&self, diffs: &Bound<'_, PyAny>, loc: i32) -> PyResult<i32> {
+        Ok(self.dmp.diff_xindex(&diffs_from_py(diffs)?, loc))
+    }
+
+    #[pyo3(name = "diff_prettyHtml")]
+    fn diff_pretty_html(&self, diffs: &Bound<'_, PyAny>) -> PyResult<String> {
+        Ok(self.dmp.diff_pretty_html(&diffs_from_py(diffs)?))
+    }
+
+    fn diff_text1(&self, diffs: &Bound<'_, PyAny>) -> PyResult<String> {
+        Ok(self.dmp.diff_text1(&mut diffs_from_py(diffs)?))
+    }
+
+    fn diff_text2(&self, diffs: &Bound<'_, PyAny>) -> PyResult<String> {
+        Ok(self.dmp.diff_text2(&mut diffs_from_py(diffs)?))
+    }
+
+    fn diff_levenshtein(&self, diffs: &Bound<'_, PyAny>) -> PyResult<i32> {
+        Ok(self.dmp.diff_levenshtein(&diffs_from_py(diffs)?))
+    }
+
+    #[pyo3(name = "diff_toDelta")]
+    fn diff_todelta(&self, diffs: &Bound<'_, PyAny>) -> PyResult<String> {
+        Ok(self.dmp.diff_todelta(&mut diffs_from_py(diffs)?))
+    }
+
+    /// Raises ValueError on a malformed delta.
+    #[pyo3(name = "diff_fromDelta")]
+    fn diff_from_delta(&self, text1: &str, delta: &str) -> PyResult<Vec<PyDiff>> {
+        self.dmp
+            .diff_parse_delta(text1, delta, super::dmp::LengthUnit::UnicodeScalar)
+            .map(|diffs| diffs_to_py(&diffs))
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    fn match_main(&self, py: Python<'_>, text: &str, pattern: &str, loc: i32) -> i32 {
+        py.detach(|| self.dmp.match_main(text, pattern, loc))
+    }
+
+    /// Compute a list of patches from two texts, from a diff, or from the
+    /// old text and a diff, like the overloads of the Python port.
+    #[pyo3(signature = (a, b=None, c=None))]
+    fn patch_make(
+        &self,
+        py: Python<'_>,
+        a: &Bound<'_, PyAny>,
+        b: Option<&Bound<'_, PyAny>>,
+        c: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<Vec<Py<PyPatch>>> {
+        let is_text = |value: &Bound<'_, PyAny>| value.is_instance_of::<PyString>();
+        let patches = match (b, c) {
+            (Some(text2), None) if is_text(a) && is_text(text2) => {
+                let (text1, text2): (String, String) = (a.extract()?, text2.extract()?);
+                py.detach(|| self.dmp.patch_make1(&text1, &text2))
+            }
+            (Some(diffs), None) if is_text(a) => {
+                let text1: String = a.extract()?;
+                self.dmp.patch_make4(&text1, &mut diffs_from_py(diffs)?)
+            }
+            (Some(_), Some(diffs)) => {
+                let text1: String = a.extract()?;
+                self.dmp.patch_make4(&text1, &mut diffs_from_py(diffs)?)
+            }
+            (None, _) => self.dmp.patch_make2(&mut diffs_from_py(a)?),
+            _ => return Err(PyValueError::new_err("unknown call format to patch_make")),
+        };
+        patches_to_py(py, &patches)
+    }
+
+    #[pyo3(name = "patch_toText")]
+    fn patch_to_text(&self, patches: &Bound<'_, PyAny>) -> PyResult<String> {
+        Ok(self.dmp.patch_to_text(&mut patches_from_py(patches)?))
+    }
+
+    /// Raises ValueError on malformed input.
+    #[pyo3(name = "patch_fromText")]
+    fn patch_from_text(&self, py: Python<'_>, textline: &str) -> PyResult<Vec<Py<PyPatch>>> {
+        let patches = self
+            .dmp
+            .patch_parse(textline)
+            .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        patches_to_py(py, &patches)
+    }
+
+    /// Merge patches onto a text.  Returns (text, results).
+    fn patch_apply(
+        &self,
+        py: Python<'_>,
+        patches: &Bound<'_, PyAny>,
+        text: &str,
+    ) -> PyResult<(String, Vec<bool>)> {
+        let mut patches = patches_from_py(patches)?;
+        let (text, results) = py.detach(|| self.dmp.patch_apply(&mut patches, text));
+        Ok((text.into_iter().collect(), results))
+    }
+}
+
+impl PyDiffMatchPatch {
+    /// Run a cleanup pass over a list of diffs, modifying it in place like
+    /// the Python port does.
+    fn cleanup(
+        &self,
+        diffs: &Bound<'_, PyList>,
+        cleanup: fn(&Dmp, &mut Vec<Diff>),
+    ) -> PyResult<()> {
+        let mut cleaned = diffs_from_py(diffs)?;
+        cleanup(&self.dmp, &mut cleaned);
+        diffs.del_slice(0, diffs.len())?;
+        for diff in &cleaned {
+            diffs.append(diff_to_py(diff))?;
+        }
+        Ok(())
+    }
+}
+
+#[pymodule]
+fn rusty_diff_match_patch(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyDiffMatchPatch>()?;
+    module.add_class::<PyPatch>()?;
+    Ok(())
+}
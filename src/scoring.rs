@@ -0,0 +1,247 @@
+/*Boundary scores for diff_cleanup_semantic_lossless.
+
+An edit surrounded by equalities can often slide left or right without
+changing what it does, diff_cleanup_semantic_lossless slides it to where
+the two boundaries score best.  The prose scores of the other ports favour
+blank lines, line breaks, sentence ends and whitespace.  The code scores
+favour line boundaries picked by the indent heuristic of git, then
+brackets, the end of indentation and identifier boundaries such as
+camelCase and snake_case.  The Unicode scores, see segment.rs, find words
+and sentences in scripts written without spaces too.
+
+Other kinds of text, such as markdown or HTML, bring their own
+BoundaryScorer.
+*/
+
+use std::fmt;
+use std::sync::Arc;
+
+use super::dmp::Dmp;
+
+/// Scores where diff_cleanup_semantic_lossless slides edits to.
+pub trait BoundaryScorer: Send + Sync {
+    /// Given two strings, compute a score representing whether the
+    /// internal boundary falls on a logical boundary, higher is better.
+    /// An edit is placed where the sum of the scores of its two boundaries
+    /// is highest, the rightmost place on ties.
+    ///
+    /// Args:
+    ///     one: First chars, empty at the start of the text.
+    ///     two: Second chars, empty at the end of the text.
+    ///
+    /// Returns:
+    ///     The score.
+    fn score(&self, one: &[char], two: &[char]) -> i32;
+}
+
+/// Which scores diff_cleanup_semantic_lossless uses.
+#[derive(Clone, Default)]
+pub enum BoundaryScoring {
+    // Blank lines, line breaks, sentence ends, whitespace, punctuation.
+    #[default]
+    Prose,
+    // Lines by indentation, brackets, identifier boundaries.
+    Code,
+    // Like Prose, by Unicode words and sentences in any script.
+    Unicode,
+    // Scores of the caller.
+    Custom(Arc<dyn BoundaryScorer>),
+}
+
+impl BoundaryScoring {
+    pub fn custom(scorer: impl BoundaryScorer + 'static) -> BoundaryScoring {
+        BoundaryScoring::Custom(Arc::new(scorer))
+    }
+}
+
+impl fmt::Debug for BoundaryScoring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoundaryScoring::Prose => write!(f, "Prose"),
+            BoundaryScoring::Code => write!(f, "Code"),
+            BoundaryScoring::Unicode => write!(f, "Unicode"),
+            BoundaryScoring::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
+
+/// Custom scorers are equal when they are the same scorer.
+impl PartialEq for BoundaryScoring {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (BoundaryScoring::Prose, BoundaryScoring::Prose) => true,
+            (BoundaryScoring::Code, BoundaryScoring::Code) => true,
+            (BoundaryScoring::Unicode, BoundaryScoring::Unicode) => true,
+            (BoundaryScoring::Custom(one), BoundaryScoring::Custom(two)) => Arc::ptr_eq(one, two),
+            _ => false,
+        }
+    }
+}
+
+/// The built-in scores, so custom scorers can fall back on them.
+impl BoundaryScorer for BoundaryScoring {
+    fn score(&self, one: &[char], two: &[char]) -> i32 {
+        match self {
+            BoundaryScoring::Prose => Dmp::diff_cleanup_semantic_score(one, two),
+            BoundaryScoring::Code => Dmp::diff_cleanup_code_score(one, two),
+            BoundaryScoring::Unicode => Dmp::diff_cleanup_unicode_score(one, two),
+            BoundaryScoring::Custom(scorer) => scorer.score(one, two),
+        }
+    }
+}
+
+// Every line boundary beats every boundary inside a line.
+const LINE_BOUNDARY: i32 = 10000;
+
+// Penalties of the indent heuristic of git, see xdiff/xdiffi.c.
+const MAX_BLANKS: usize = 20;
+const START_OF_FILE_PENALTY: i32 = 1;
+const END_OF_FILE_PENALTY: i32 = 21;
+const TOTAL_BLANK_WEIGHT: i32 = -30;
+const POST_BLANK_WEIGHT: i32 = 6;
+const RELATIVE_INDENT_PENALTY: i32 = -4;
+const RELATIVE_INDENT_WITH_BLANK_PENALTY: i32 = 10;
+const RELATIVE_OUTDENT_PENALTY: i32 = 24;
+const RELATIVE_OUTDENT_WITH_BLANK_PENALTY: i32 = 17;
+const RELATIVE_DEDENT_PENALTY: i32 = 23;
+const RELATIVE_DEDENT_WITH_BLANK_PENALTY: i32 = 17;
+/*git prefers the less indented of two splits by a flat 60, whatever the
+difference.  Scores here are added up per boundary, so the indent is
+weighed per column instead.*/
+const INDENT_WEIGHT: i32 = 15;
+
+/// Columns of indentation of a line, tabs stopping at multiples of 8.
+/// None for a blank line.
+fn indent(line: &[char]) -> Option<i32> {
+    let mut columns = 0;
+    for ch in line {
+        match ch {
+            ' ' => columns += 1,
+            '\t' => columns += 8 - columns % 8,
+            ch if ch.is_whitespace() => {}
+            _ => return Some(columns),
+        }
+    }
+    None
+}
+
+/// First non-blank line of lines and the number of blank lines before it.
+fn first_indent<'a>(lines: impl Iterator<Item = &'a [char]>) -> (usize, Option<i32>) {
+    let mut blanks = 0;
+    for line in lines {
+        if let Some(indent) = indent(line) {
+            return (blanks, Some(indent));
+        }
+        blanks += 1;
+        if blanks == MAX_BLANKS {
+            return (blanks, Some(0));
+        }
+    }
+    (blanks, None)
+}
+
+/// Penalty of git for splitting a group of changed lines between the
+/// lines of one and those of two, lower is better.
+fn split_penalty(one: &[char], two: &[char]) -> i32 {
+    let before = one.strip_suffix(&['\n']).unwrap_or(one);
+    let mut after = two
+        .strip_suffix(&['\n'])
+        .unwrap_or(two)
+        .split(|ch| *ch == '\n');
+    let split_line = if two.is_empty() { None } else { after.next() };
+    let split_indent = split_line.and_then(indent);
+    let (pre_blank, pre_indent) = if one.is_empty() {
+        (0, None)
+    } else {
+        first_indent(before.rsplit(|ch| *ch == '\n'))
+    };
+    let (post_blank, post_indent) = first_indent(after);
+
+    let post_blank = match (split_line, split_indent) {
+        (Some(_), None) => 1 + post_blank as i32,
+        _ => 0,
+    };
+    let total_blank = pre_blank as i32 + post_blank;
+    let any_blanks = total_blank != 0;
+    let mut penalty = TOTAL_BLANK_WEIGHT * total_blank + POST_BLANK_WEIGHT * post_blank;
+    if pre_indent.is_none() && pre_blank == 0 {
+        penalty += START_OF_FILE_PENALTY;
+    }
+    if split_line.is_none() {
+        penalty += END_OF_FILE_PENALTY;
+    }
+    let indent = split_indent.or(post_indent);
+    penalty += INDENT_WEIGHT * indent.unwrap_or(-1);
+    if let (Some(indent), Some(pre_indent)) = (indent, pre_indent) {
+        penalty += if indent > pre_indent {
+            if any_blanks {
+                RELATIVE_INDENT_WITH_BLANK_PENALTY
+            } else {
+                RELATIVE_INDENT_PENALTY
+            }
+        } else if indent == pre_indent {
+            0
+        } else if post_indent.is_some_and(|post_indent| post_indent > indent) {
+            if any_blanks {
+                RELATIVE_OUTDENT_WITH_BLANK_PENALTY
+            } else {
+                RELATIVE_OUTDENT_PENALTY
+            }
+        } else if any_blanks {
+            RELATIVE_DEDENT_WITH_BLANK_PENALTY
+        } else {
+            RELATIVE_DEDENT_PENALTY
+        };
+    }
+    penalty
+}
+
+impl Dmp {
+    /// Given two strings, compute a score representing whether the
+    /// internal boundary falls on a logical boundary of source code.
+    /// Line boundaries score around 10000, ranked by the indent heuristic
+    /// of git; inside a line scores range from 6 (best) to 0 (worst).
+    ///
+    /// Args:
+    ///     one: First chars.
+    ///     two: Second chars.
+    ///
+    /// Returns:
+    ///     The score.
+    pub(crate) fn diff_cleanup_code_score(one: &[char], two: &[char]) -> i32 {
+        if one.is_empty() || two.is_empty() || one[one.len() - 1] == '\n' {
+            return LINE_BOUNDARY - split_penalty(one, two);
+        }
+        let char1 = one[one.len() - 1];
+        let char2 = two[0];
+        if matches!(char1, '(' | '[' | '{') || matches!(char2, ')' | ']' | '}') {
+            // Six points for the inside of brackets.
+            return 6;
+        }
+        let line_start = one
+            .iter()
+            .rposition(|ch| *ch == '\n')
+            .map_or(0, |index| index + 1);
+        if !char2.is_whitespace() && one[line_start..].iter().all(|ch| ch.is_whitespace()) {
+            // Five points for the end of indentation.
+            return 5;
+        }
+        if char1.is_whitespace() || char2.is_whitespace() {
+            // Three points for whitespace.
+            return 3;
+        }
+        if !char1.is_alphanumeric() || !char2.is_alphanumeric() {
+            // Two points for punctuation, including snake_case.
+            return 2;
+        }
+        let camel_case = (char1.is_lowercase() || char1.is_numeric()) && char2.is_uppercase();
+        let acronym_end = char1.is_uppercase()
+            && char2.is_uppercase()
+            && two.get(1).is_some_and(|ch| ch.is_lowercase());
+        if camel_case || acronym_end {
+            // Two points for camelCase, as in fooBar or HTTPServer.
+            return 2;
+        }
+        0
+    }
+}
@@ -0,0 +1,202 @@
+/*Word and sentence boundaries of text in any script.
+
+The prose scores take whitespace and punctuation as word boundaries, which
+Chinese, Japanese, Thai and other scripts written without spaces do not
+have, so edits in such text are left wherever the diff put them.  The
+Unicode scores use the word and sentence boundaries of UAX #29 instead,
+and never split a grapheme cluster, such as a letter and its combining
+marks.
+
+UAX #29 cannot find words in these scripts without a dictionary: it breaks
+between every Han ideograph, kana or Thai letter.  Such breaks are not
+scored as word boundaries, only changes of script are.  So without the
+dictionary feature Chinese, Thai, Lao, Khmer and Burmese text has no word
+boundaries inside a run of the script at all, only at sentence ends,
+whitespace and punctuation.  Japanese gets a rough guess: a change of
+script is where most words start, except for kana ending a word written
+in Han, which is told apart from a following particle by a short list of
+particles.  diff_words splits such runs into single chars.
+
+The dictionary feature segments them with the dictionaries and models of
+ICU4X instead.
+*/
+
+use unicode_segmentation::UnicodeSegmentation;
+
+#[cfg(feature = "dictionary")]
+use std::sync::OnceLock;
+
+#[cfg(feature = "dictionary")]
+use icu_segmenter::{options::WordBreakInvariantOptions, WordSegmenter, WordSegmenterBorrowed};
+
+use super::dmp::{Deadline, Diff, Dmp};
+use super::normalize::Token;
+
+// Chars on either side of a boundary looked at to score it.
+const CONTEXT: usize = 32;
+
+/// Scripts written without spaces between words.
+#[cfg(not(feature = "dictionary"))]
+#[derive(PartialEq)]
+enum Script {
+    Han,
+    Hiragana,
+    Thai,
+    Lao,
+    Khmer,
+    Myanmar,
+}
+
+#[cfg(not(feature = "dictionary"))]
+fn script(ch: char) -> Option<Script> {
+    match ch {
+        '\u{3005}' | '\u{3007}' | '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' => {
+            Some(Script::Han)
+        }
+        '\u{F900}'..='\u{FAFF}' | '\u{20000}'..='\u{3FFFF}' => Some(Script::Han),
+        '\u{3041}'..='\u{309F}' => Some(Script::Hiragana),
+        '\u{0E00}'..='\u{0E7F}' => Some(Script::Thai),
+        '\u{0E80}'..='\u{0EFF}' => Some(Script::Lao),
+        '\u{1780}'..='\u{17FF}' => Some(Script::Khmer),
+        '\u{1000}'..='\u{109F}' => Some(Script::Myanmar),
+        _ => None,
+    }
+}
+
+// Japanese particles, which follow a word.
+#[cfg(not(feature = "dictionary"))]
+const PARTICLES: [char; 10] = ['を', 'は', 'が', 'に', 'で', 'と', 'の', 'も', 'へ', 'や'];
+
+/// Byte offsets of the word boundaries of text in order, including 0 and
+/// its length.
+#[cfg(not(feature = "dictionary"))]
+fn word_bounds(text: &str) -> impl Iterator<Item = usize> + '_ {
+    text.split_word_bound_indices()
+        .map(|(index, _)| index)
+        .chain([text.len()])
+}
+
+#[cfg(feature = "dictionary")]
+fn word_bounds(text: &str) -> impl Iterator<Item = usize> + '_ {
+    // Loading the dictionaries and models is far slower than segmenting the
+    // few chars around a boundary, so it is done once.
+    static SEGMENTER: OnceLock<WordSegmenterBorrowed<'static>> = OnceLock::new();
+    SEGMENTER
+        .get_or_init(|| WordSegmenter::new_auto(WordBreakInvariantOptions::default()))
+        .segment_str(text)
+}
+
+/// Is index of text a boundary between two words?
+fn is_word_boundary(text: &str, index: usize) -> bool {
+    // Segment no further than index.
+    if !word_bounds(text)
+        .take_while(|bound| *bound <= index)
+        .any(|bound| bound == index)
+    {
+        return false;
+    }
+    #[cfg(not(feature = "dictionary"))]
+    {
+        // UAX #29 breaks between every char of these scripts.  Kana after
+        // a Han ideograph usually is the inflected end of the same word,
+        // unless it is a particle.
+        let before = text[..index].chars().next_back();
+        let after = text[index..].chars().next();
+        let (script1, script2) = (before.and_then(script), after.and_then(script));
+        if script1.is_some() && script1 == script2 {
+            return false;
+        }
+        if script1 == Some(Script::Han)
+            && script2 == Some(Script::Hiragana)
+            && !after.is_some_and(|ch| PARTICLES.contains(&ch))
+        {
+            return false;
+        }
+    }
+    true
+}
+
+impl Dmp {
+    /// Given two strings, compute a score representing whether the
+    /// internal boundary falls on a word or sentence boundary of Unicode
+    /// text in any script.  Without the dictionary feature there are no
+    /// word boundaries inside Chinese or Thai text, see the module docs.
+    /// Scores range from 6 (best) to 0 (worst), and are far below 0 inside
+    /// a grapheme cluster.
+    ///
+    /// Args:
+    ///     one: First chars.
+    ///     two: Second chars.
+    ///
+    /// Returns:
+    ///     The score.
+    pub(crate) fn diff_cleanup_unicode_score(one: &[char], two: &[char]) -> i32 {
+        if one.is_empty() || two.is_empty() {
+            // Edges are the best.
+            return 6;
+        }
+        let mut text: String = one[one.len().saturating_sub(CONTEXT)..].iter().collect();
+        let index = text.len();
+        text.extend(&two[..two.len().min(CONTEXT)]);
+
+        if !text.grapheme_indices(true).any(|(start, _)| start == index) {
+            // Never split a char from its combining marks.
+            return -100;
+        }
+        let prose = Dmp::diff_cleanup_semantic_score(one, two);
+        if prose >= 4 {
+            // Blank lines and line breaks.
+            return prose;
+        }
+        if text
+            .split_sentence_bound_indices()
+            .any(|(start, _)| start == index)
+        {
+            // Three points for the end of sentences.
+            return 3;
+        }
+        if prose >= 2 {
+            // Whitespace.
+            return prose;
+        }
+        if is_word_boundary(&text, index) {
+            // One point for other word boundaries.
+            return 1;
+        }
+        0
+    }
+
+    /// Find the differences between two texts word by word, without
+    /// refining changed words character by character.  Words are found
+    /// with UAX #29, or the dictionaries of the dictionary feature for
+    /// scripts written without spaces, which are one char per word
+    /// without it; whitespace and punctuation are words of their own.  Words are compared with the ignore_case and
+    /// normalization options.
+    ///
+    /// Args:
+    ///     text1: Old text to be diffed.
+    ///     text2: New text to be diffed.
+    ///
+    /// Returns:
+    ///     Vector of diffs made of whole words.
+    pub fn diff_words(&self, text1: &str, text2: &str) -> Vec<Diff> {
+        let tokens1 = self.word_tokenize(text1);
+        let tokens2 = self.word_tokenize(text2);
+        self.diff_tokens(&tokens1, &tokens2, Deadline::now())
+    }
+
+    fn word_tokenize<'a>(&self, text: &'a str) -> Vec<Token<'a>> {
+        let bounds: Vec<usize> = word_bounds(text).collect();
+        bounds
+            .windows(2)
+            .map(|bounds| {
+                let word = &text[bounds[0]..bounds[1]];
+                Token {
+                    key: self.normalize_text(word),
+                    text: word,
+                    ignored: false,
+                }
+            })
+            .collect()
+    }
+}
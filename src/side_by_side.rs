@@ -0,0 +1,141 @@
+/*Side-by-side view of a line-mode diff.
+
+Deleted and inserted lines of the same edit are paired up as modified rows,
+whatever is left over is padded with an empty cell on the other side.
+*/
+
+use super::dmp::{Diff, Dmp};
+
+/// What happened to the line(s) of a row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowKind {
+    Equal,
+    Deleted,
+    Added,
+    Modified,
+}
+
+/// One aligned row of a side-by-side view.  Line numbers start at 1 and
+/// line texts come without their terminator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub old: Option<(usize, String)>,
+    pub new: Option<(usize, String)>,
+    pub kind: RowKind,
+    // Character diffs from the old to the new line of a modified row.
+    pub inline: Option<Vec<Diff>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SideBySide {
+    pub rows: Vec<Row>,
+}
+
+/// Drop the line terminator from a line.
+fn strip_newline(line: &str) -> String {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    line.strip_suffix('\r').unwrap_or(line).to_string()
+}
+
+impl Dmp {
+    /// Lay out a line-mode diff as aligned rows for a two-pane view.
+    ///
+    /// Args:
+    ///     diffs: Vector of diffs made of whole lines, e.g. from diff_lines.
+    ///     inline: Also compute character diffs for modified rows.
+    ///
+    /// Returns:
+    ///     SideBySide view model.
+    pub fn diff_side_by_side(&self, diffs: &[Diff], inline: bool) -> SideBySide {
+        let mut view = SideBySide::default();
+        let mut line1 = 0;
+        let mut line2 = 0;
+        let mut deleted: Vec<String> = vec![];
+        let mut added: Vec<String> = vec![];
+
+        let lines = self.diff_split_lines(diffs);
+        for line in &lines {
+            match line {
+                Diff::Delete(txt) => deleted.push(strip_newline(txt)),
+                Diff::Add(txt) => added.push(strip_newline(txt)),
+                Diff::Keep(txt) => {
+                    self.side_by_side_flush(
+                        &mut view,
+                        (&mut line1, &mut line2),
+                        (&mut deleted, &mut added),
+                        inline,
+                    );
+                    line1 += 1;
+                    line2 += 1;
+                    let txt = strip_newline(txt);
+                    view.rows.push(Row {
+                        old: Some((line1, txt.clone())),
+                        new: Some((line2, txt)),
+                        kind: RowKind::Equal,
+                        inline: None,
+                    });
+                }
+            }
+        }
+        self.side_by_side_flush(
+            &mut view,
+            (&mut line1, &mut line2),
+            (&mut deleted, &mut added),
+            inline,
+        );
+        view
+    }
+
+    /// Turn the pending deleted and added lines of one edit into rows.
+    fn side_by_side_flush(
+        &self,
+        view: &mut SideBySide,
+        (line1, line2): (&mut usize, &mut usize),
+        (deleted, added): (&mut Vec<String>, &mut Vec<String>),
+        inline: bool,
+    ) {
+        let mut deleted = deleted.drain(..);
+        let mut added = added.drain(..);
+        loop {
+            let row = match (deleted.next(), added.next()) {
+                (None, None) => break,
+                (Some(old), Some(new)) => {
+                    *line1 += 1;
+                    *line2 += 1;
+                    let changes = if inline {
+                        let mut changes = self.diff_main(&old, &new, false);
+                        self.diff_cleanup_semantic(&mut changes);
+                        Some(changes)
+                    } else {
+                        None
+                    };
+                    Row {
+                        old: Some((*line1, old)),
+                        new: Some((*line2, new)),
+                        kind: RowKind::Modified,
+                        inline: changes,
+                    }
+                }
+                (Some(old), None) => {
+                    *line1 += 1;
+                    Row {
+                        old: Some((*line1, old)),
+                        new: None,
+                        kind: RowKind::Deleted,
+                        inline: None,
+                    }
+                }
+                (None, Some(new)) => {
+                    *line2 += 1;
+                    Row {
+                        old: None,
+                        new: Some((*line2, new)),
+                        kind: RowKind::Added,
+                        inline: None,
+                    }
+                }
+            };
+            view.rows.push(row);
+        }
+    }
+}
@@ -0,0 +1,99 @@
+/*Similarity of texts.
+
+The similarity index turns the Levenshtein distance of two texts into a
+score between 0 (nothing in common) and 1 (equal), which is what rename and
+copy detection compare against a threshold, like the -M50% of git.
+*/
+
+use super::dmp::Dmp;
+
+/// A deleted and an added text paired up by similarity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimilarPair {
+    // Index into the deleted texts.
+    pub deleted: usize,
+    // Index into the added texts.
+    pub added: usize,
+    pub score: f32,
+}
+
+impl Dmp {
+    /// Compute how similar two texts are: one minus their Levenshtein
+    /// distance divided by the length of the longer text.
+    ///
+    /// Args:
+    ///     text1: Old text.
+    ///     text2: New text.
+    ///     lines: If true, compare whole lines, hashed the way diff_lines
+    ///         does, so a changed line counts with its full length.  If false,
+    ///         compare characters.
+    ///
+    /// Returns:
+    ///     Score from 0.0 (nothing in common) to 1.0 (equal texts).
+    pub fn similarity(&self, text1: &str, text2: &str, lines: bool) -> f32 {
+        let longest = text1.chars().count().max(text2.chars().count());
+        if longest == 0 {
+            return 1.0;
+        }
+        let diffs = if lines {
+            self.diff_lines(text1, text2)
+        } else {
+            self.diff_main(text1, text2, true)
+        };
+        1.0 - self.diff_levenshtein(&diffs) as f32 / longest as f32
+    }
+
+    /// Pair deleted texts with added ones, most similar pairs first.  Every
+    /// text takes part in at most one pair.
+    ///
+    /// Args:
+    ///     deleted: Deleted texts, e.g. the contents of removed files.
+    ///     added: Added texts, e.g. the contents of new files.
+    ///     threshold: Lowest similarity worth pairing, 0.5 for git's -M50%.
+    ///     lines: Compare whole lines, see similarity.
+    ///
+    /// Returns:
+    ///     Vector of pairs, ordered by deleted index.
+    pub fn similarity_pairs(
+        &self,
+        deleted: &[&str],
+        added: &[&str],
+        threshold: f32,
+        lines: bool,
+    ) -> Vec<SimilarPair> {
+        let mut candidates: Vec<SimilarPair> = vec![];
+        for (i, text1) in deleted.iter().enumerate() {
+            for (j, text2) in added.iter().enumerate() {
+                // The distance is at least the difference in length.
+                let (len1, len2) = (text1.chars().count(), text2.chars().count());
+                let (shorter, longer) = (len1.min(len2), len1.max(len2));
+                if longer > 0 && (shorter as f32 / longer as f32) < threshold {
+                    continue;
+                }
+                let score = self.similarity(text1, text2, lines);
+                if score >= threshold {
+                    candidates.push(SimilarPair {
+                        deleted: i,
+                        added: j,
+                        score,
+                    });
+                }
+            }
+        }
+        // Best score first, ties go to the earlier texts.
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+        let mut deleted_used = vec![false; deleted.len()];
+        let mut added_used = vec![false; added.len()];
+        let mut pairs: Vec<SimilarPair> = vec![];
+        for pair in candidates {
+            if !deleted_used[pair.deleted] && !added_used[pair.added] {
+                deleted_used[pair.deleted] = true;
+                added_used[pair.added] = true;
+                pairs.push(pair);
+            }
+        }
+        pairs.sort_by_key(|pair| pair.deleted);
+        pairs
+    }
+}
@@ -0,0 +1,192 @@
+/*Diff statistics.
+
+Counts what a diff inserts, deletes and keeps in characters, words and
+lines, and renders per-file counts the way `git diff --stat` does.  Words
+are runs of non-whitespace.  Words and lines are counted on a word and a
+line diff of the two texts, so a line with any change in it counts as one
+deleted and one inserted line, like diff tools report it.
+*/
+
+use super::dmp::{Deadline, Diff, Dmp};
+use super::normalize::Token;
+
+/// Summary of a diff.  Lengths are in Unicode scalar values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DiffStats {
+    pub inserted_chars: usize,
+    pub deleted_chars: usize,
+    pub unchanged_chars: usize,
+    pub inserted_words: usize,
+    pub deleted_words: usize,
+    pub unchanged_words: usize,
+    pub inserted_lines: usize,
+    pub deleted_lines: usize,
+    pub unchanged_lines: usize,
+    // Number of runs of insertions and deletions between equalities.
+    pub edit_regions: usize,
+    // Levenshtein distance in chars.
+    pub levenshtein: usize,
+}
+
+/// Split a text into runs of whitespace and runs of anything else.
+fn word_tokenize(text: &str) -> Vec<Token<'_>> {
+    let mut tokens: Vec<Token> = vec![];
+    let mut start = 0;
+    let mut space = false;
+    for (i, ch) in text.char_indices() {
+        if i > start && ch.is_whitespace() != space {
+            tokens.push(Token {
+                key: text[start..i].to_string(),
+                text: &text[start..i],
+                ignored: false,
+            });
+            start = i;
+        }
+        space = ch.is_whitespace();
+    }
+    if start < text.len() {
+        tokens.push(Token {
+            key: text[start..].to_string(),
+            text: &text[start..],
+            ignored: false,
+        });
+    }
+    tokens
+}
+
+/// Scale a count down to a bar of at most width chars, keeping non-zero
+/// counts visible.
+fn scale_linear(count: usize, width: usize, max_change: usize) -> usize {
+    if count == 0 {
+        0
+    } else {
+        1 + count * (width - 1) / max_change
+    }
+}
+
+fn plural(count: usize, one: &str, many: &str) -> String {
+    format!("{count} {}", if count == 1 { one } else { many })
+}
+
+impl Dmp {
+    /// Count the characters, words and lines a diff inserts, deletes and
+    /// keeps.
+    ///
+    /// Args:
+    ///     diffs: Vector of diff object.
+    ///
+    /// Returns:
+    ///     DiffStats summary.
+    pub fn diff_stats(&self, diffs: &[Diff]) -> DiffStats {
+        let mut stats = DiffStats {
+            levenshtein: self.diff_levenshtein(&diffs.to_vec()) as usize,
+            ..DiffStats::default()
+        };
+        let mut in_edit = false;
+        for diff in diffs {
+            let count = diff.text().chars().count();
+            match diff {
+                Diff::Add(_) => stats.inserted_chars += count,
+                Diff::Delete(_) => stats.deleted_chars += count,
+                Diff::Keep(_) => stats.unchanged_chars += count,
+            }
+            let edit = !matches!(diff, Diff::Keep(_));
+            if edit && !in_edit {
+                stats.edit_regions += 1;
+            }
+            in_edit = edit;
+        }
+
+        let text1 = self.diff_text1(&mut diffs.to_vec());
+        let text2 = self.diff_text2(&mut diffs.to_vec());
+        let words = self.diff_tokens(
+            &word_tokenize(&text1),
+            &word_tokenize(&text2),
+            Deadline::now(),
+        );
+        for diff in &words {
+            let count = diff.text().split_whitespace().count();
+            match diff {
+                Diff::Add(_) => stats.inserted_words += count,
+                Diff::Delete(_) => stats.deleted_words += count,
+                Diff::Keep(_) => stats.unchanged_words += count,
+            }
+        }
+        for diff in &self.diff_lines(&text1, &text2) {
+            let count = diff.text().split_inclusive('\n').count();
+            match diff {
+                Diff::Add(_) => stats.inserted_lines += count,
+                Diff::Delete(_) => stats.deleted_lines += count,
+                Diff::Keep(_) => stats.unchanged_lines += count,
+            }
+        }
+        stats
+    }
+
+    /// Render line counts of several files like `git diff --stat`, one
+    /// "name | count +++--" line per file and a line with the totals.
+    /// Bars are scaled down when they would not fit in width columns.
+    ///
+    /// Args:
+    ///     files: Name and statistics of every file.
+    ///     width: Total width of a line.
+    ///
+    /// Returns:
+    ///     Text of the summary, one line per file plus a total.
+    pub fn diff_stats_to_text(&self, files: &[(&str, DiffStats)], width: usize) -> String {
+        let name_width = files
+            .iter()
+            .map(|(name, _)| name.chars().count())
+            .max()
+            .unwrap_or(0);
+        let max_change = files
+            .iter()
+            .map(|(_, stats)| stats.inserted_lines + stats.deleted_lines)
+            .max()
+            .unwrap_or(0);
+        let count_width = max_change.to_string().len();
+        // " name | count bar"
+        let bar_width = width.saturating_sub(name_width + count_width + 5).max(1);
+
+        let mut text = "".to_string();
+        let mut insertions = 0;
+        let mut deletions = 0;
+        for (name, stats) in files {
+            let mut add = stats.inserted_lines;
+            let mut del = stats.deleted_lines;
+            insertions += add;
+            deletions += del;
+            let total = add + del;
+            if max_change > bar_width {
+                let mut scaled = scale_linear(total, bar_width, max_change);
+                if scaled < 2 && add > 0 && del > 0 {
+                    scaled = 2;
+                }
+                if add < del {
+                    add = scale_linear(add, bar_width, max_change);
+                    del = scaled - add;
+                } else {
+                    del = scale_linear(del, bar_width, max_change);
+                    add = scaled - del;
+                }
+            }
+            let line = format!(
+                " {name:<name_width$} | {total:>count_width$} {}{}",
+                "+".repeat(add),
+                "-".repeat(del)
+            );
+            text += line.trim_end();
+            text += "\n";
+        }
+
+        text += &format!(" {}", plural(files.len(), "file changed", "files changed"));
+        if insertions > 0 || deletions == 0 {
+            text += &format!(", {}", plural(insertions, "insertion(+)", "insertions(+)"));
+        }
+        if deletions > 0 || insertions == 0 {
+            text += &format!(", {}", plural(deletions, "deletion(-)", "deletions(-)"));
+        }
+        text += "\n";
+        text
+    }
+}
@@ -0,0 +1,314 @@
+/*Differential synchronization.
+
+Neil Fraser's shadow-copy algorithm, in its guaranteed delivery form.  Each
+end of a link keeps a shadow: its idea of the text the other end last saw.
+A sync cycle diffs the live text against the shadow, pushes the delta onto
+a stack of edits and sends every edit the other end has not acknowledged.
+The receiver applies each delta strictly to its own shadow, which has to
+match exactly, and fuzzily to its live text with patch_apply, so edits made
+in the meantime on either side survive.
+
+Every edit carries the version of the shadow it was made against, and every
+message acknowledges the versions received so far, so a lost message is
+made up for by sending the edit stack again.  When a reply is lost after
+the receiver already moved its shadow on, the backup shadow holds the
+version the other end still has.
+
+Cycles are half duplex: a client sends, the server replies.  A SyncServer
+keeps one SyncPeer per client against the shared text.
+*/
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use super::dmp::{Dmp, LengthUnit, ParseError};
+
+/// The delta turning a shadow at version into the next version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncEdit {
+    pub version: u64,
+    pub delta: String,
+}
+
+/// What one end of a link sends the other in a cycle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncMessage {
+    // Versions of the other end received so far.
+    pub ack: u64,
+    // Edits the other end has not acknowledged yet, oldest first.
+    pub edits: Vec<SyncEdit>,
+}
+
+/// Why a message could not be merged.  Either way the two ends no longer
+/// share a shadow and the client has to start over.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncError {
+    // The message builds on a version this end cannot restore.
+    VersionMismatch { expected: u64, received: u64 },
+    // A delta does not fit the shadow it was made against.
+    Delta(ParseError),
+}
+
+impl Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncError::VersionMismatch { expected, received } => {
+                write!(f, "expected version {expected}, received {received}")
+            }
+            SyncError::Delta(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Error for SyncError {}
+
+impl From<ParseError> for SyncError {
+    fn from(err: ParseError) -> Self {
+        SyncError::Delta(err)
+    }
+}
+
+/// One end of a link: the shadows and edits kept for the other end.
+#[derive(Debug, Clone, Default)]
+pub struct SyncPeer {
+    shadow: String,
+    // Versions of this end, and of the other end, the shadow includes.
+    local_version: u64,
+    remote_version: u64,
+    backup: String,
+    backup_version: u64,
+    edits: Vec<SyncEdit>,
+}
+
+impl SyncPeer {
+    /// Start a link with empty shadows, so the first cycle sends the whole
+    /// text.
+    pub fn new() -> SyncPeer {
+        SyncPeer::default()
+    }
+
+    pub fn shadow(&self) -> &str {
+        &self.shadow
+    }
+
+    /// Record the changes of text since the last cycle and build the
+    /// message for the other end.
+    ///
+    /// Args:
+    ///     dmp: Settings for diffing.
+    ///     text: Live text of this end.
+    ///
+    /// Returns:
+    ///     Message carrying every unacknowledged edit.
+    pub fn make_message(&mut self, dmp: &Dmp, text: &str) -> SyncMessage {
+        let mut diffs = dmp.diff_main(&self.shadow, text, true);
+        dmp.diff_cleanup_efficiency(&mut diffs);
+        if diffs.iter().any(|diff| diff.op() != 0) {
+            self.edits.push(SyncEdit {
+                version: self.local_version,
+                delta: dmp.diff_todelta(&mut diffs),
+            });
+            self.shadow = text.to_string();
+            self.local_version += 1;
+        }
+        SyncMessage {
+            ack: self.remote_version,
+            edits: self.edits.clone(),
+        }
+    }
+
+    /// Merge a message from the other end into the shadow and text.
+    ///
+    /// Args:
+    ///     dmp: Settings for patching.
+    ///     text: Live text of this end, patched in place.
+    ///     message: Message from the other end.
+    ///
+    /// Returns:
+    ///     Nothing, or why the message does not fit.
+    pub fn receive(
+        &mut self,
+        dmp: &Dmp,
+        text: &mut String,
+        message: &SyncMessage,
+    ) -> Result<(), SyncError> {
+        if message.ack != self.local_version {
+            if message.ack != self.backup_version {
+                return Err(SyncError::VersionMismatch {
+                    expected: self.local_version,
+                    received: message.ack,
+                });
+            }
+            // Our last message was lost after the shadow moved on; the text
+            // still holds those changes, so they are diffed again next cycle.
+            self.shadow = self.backup.clone();
+            self.local_version = self.backup_version;
+            self.edits.clear();
+        }
+        // The other end has our shadow, keep it in case our reply is lost,
+        // even when the message brings no edits.
+        self.backup = self.shadow.clone();
+        self.backup_version = self.local_version;
+        self.edits.retain(|edit| edit.version >= message.ack);
+
+        for edit in &message.edits {
+            if edit.version < self.remote_version {
+                // Sent again because our acknowledgement was lost.
+                continue;
+            }
+            if edit.version > self.remote_version {
+                return Err(SyncError::VersionMismatch {
+                    expected: self.remote_version,
+                    received: edit.version,
+                });
+            }
+            let mut diffs =
+                dmp.diff_parse_delta(&self.shadow, &edit.delta, LengthUnit::UnicodeScalar)?;
+            let mut patches = dmp.patch_make4(&self.shadow, &mut diffs);
+            self.shadow = dmp.diff_text2(&mut diffs);
+            self.remote_version += 1;
+            self.backup = self.shadow.clone();
+            self.backup_version = self.local_version;
+            *text = dmp.patch_apply(&mut patches, text).0.into_iter().collect();
+        }
+        Ok(())
+    }
+}
+
+/// The shared text and one link per client.
+#[derive(Debug, Clone, Default)]
+pub struct SyncServer {
+    pub text: String,
+    peers: HashMap<u64, SyncPeer>,
+}
+
+impl SyncServer {
+    pub fn new(text: &str) -> SyncServer {
+        SyncServer {
+            text: text.to_string(),
+            peers: HashMap::new(),
+        }
+    }
+
+    /// Merge a message from a client and build the reply.  Unknown clients
+    /// get a new link.
+    ///
+    /// Args:
+    ///     dmp: Settings for diffing and patching.
+    ///     client_id: Who sent the message.
+    ///     message: Message from the client.
+    ///
+    /// Returns:
+    ///     Reply for the client, or why the message does not fit.
+    pub fn handle(
+        &mut self,
+        dmp: &Dmp,
+        client_id: u64,
+        message: &SyncMessage,
+    ) -> Result<SyncMessage, SyncError> {
+        let peer = self.peers.entry(client_id).or_default();
+        peer.receive(dmp, &mut self.text, message)?;
+        Ok(peer.make_message(dmp, &self.text))
+    }
+
+    /// Forget a client, which has to start over with an empty shadow.
+    pub fn disconnect(&mut self, client_id: u64) {
+        self.peers.remove(&client_id);
+    }
+}
+
+/// Carries a client message to the server and the reply back.
+pub trait SyncTransport {
+    /// Returns:
+    ///     The reply, None if the message or the reply was lost.
+    fn exchange(
+        &mut self,
+        client_id: u64,
+        message: SyncMessage,
+    ) -> Result<Option<SyncMessage>, SyncError>;
+}
+
+/// A client: the live text and its link to the server.
+#[derive(Debug, Clone)]
+pub struct SyncClient {
+    pub id: u64,
+    pub text: String,
+    peer: SyncPeer,
+}
+
+impl SyncClient {
+    pub fn new(id: u64, text: &str) -> SyncClient {
+        SyncClient {
+            id,
+            text: text.to_string(),
+            peer: SyncPeer::new(),
+        }
+    }
+
+    /// Run one cycle: send local changes and merge the reply, if any.
+    ///
+    /// Args:
+    ///     dmp: Settings for diffing and patching.
+    ///     transport: Way to the server.
+    ///
+    /// Returns:
+    ///     Nothing, or why the client and server no longer fit.
+    pub fn sync(&mut self, dmp: &Dmp, transport: &mut impl SyncTransport) -> Result<(), SyncError> {
+        let message = self.peer.make_message(dmp, &self.text);
+        match transport.exchange(self.id, message)? {
+            Some(reply) => self.peer.receive(dmp, &mut self.text, &reply),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Transport to a server in the same process, which can lose messages on
+/// request to exercise the recovery paths.
+#[derive(Default)]
+pub struct InMemoryTransport {
+    pub server: SyncServer,
+    pub dmp: Dmp,
+    lose_messages: usize,
+    lose_replies: usize,
+}
+
+impl InMemoryTransport {
+    pub fn new(server: SyncServer, dmp: Dmp) -> InMemoryTransport {
+        InMemoryTransport {
+            server,
+            dmp,
+            lose_messages: 0,
+            lose_replies: 0,
+        }
+    }
+
+    /// Drop the next count messages before they reach the server.
+    pub fn lose_messages(&mut self, count: usize) {
+        self.lose_messages += count;
+    }
+
+    /// Drop the next count replies after the server handled the message.
+    pub fn lose_replies(&mut self, count: usize) {
+        self.lose_replies += count;
+    }
+}
+
+impl SyncTransport for InMemoryTransport {
+    fn exchange(
+        &mut self,
+        client_id: u64,
+        message: SyncMessage,
+    ) -> Result<Option<SyncMessage>, SyncError> {
+        if self.lose_messages > 0 {
+            self.lose_messages -= 1;
+            return Ok(None);
+        }
+        let reply = self.server.handle(&self.dmp, client_id, &message)?;
+        if self.lose_replies > 0 {
+            self.lose_replies -= 1;
+            return Ok(None);
+        }
+        Ok(Some(reply))
+    }
+}
@@ -0,0 +1,277 @@
+/*WebAssembly bindings with the API of the JavaScript port.
+
+The diff_match_patch class exported here has the method and property names
+of the JavaScript library, diffs are [op, text] arrays and patches are
+objects with the fields of its patch_obj, so either side can read what the
+other wrote.  Offsets count UTF-16 code units like JavaScript strings do.
+
+Only the conversion between JavaScript values and Diff or Patch lives here;
+the work is done by the _u16 functions of Dmp, which need no JavaScript
+host and are tested natively.  tests/wasm.rs runs the class itself under
+Node.js with wasm-bindgen-test, as the wasm job of .github/workflows/ci.yml
+does.  That needs the wasm32-unknown-unknown target (rustup target add
+wasm32-unknown-unknown), wasm-bindgen-test-runner from wasm-bindgen-cli of
+the same version as the wasm-bindgen crate in Cargo.lock, and Node.js.  The
+module imports JavaScript, so wasmtime and other runtimes without a
+JavaScript host cannot run it.
+
+The crate is only an rlib unless asked otherwise, and wasm-pack insists on
+a cdylib in Cargo.toml, so build the module with cargo and wasm-bindgen:
+
+    cargo rustc --lib --release --target wasm32-unknown-unknown \
+        --features wasm --crate-type cdylib
+    wasm-bindgen --out-dir pkg \
+        target/wasm32-unknown-unknown/release/rusty_diff_match_patch.wasm
+*/
+
+use std::time::Duration;
+
+use js_sys::{Array, Error, Object, Reflect};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use super::dmp::{Diff, Dmp, Patch};
+
+fn type_error(message: &str) -> JsValue {
+    js_sys::TypeError::new(message).into()
+}
+
+fn diff_to_js(diff: &Diff) -> JsValue {
+    Array::of2(&JsValue::from(diff.op()), &JsValue::from_str(diff.text())).into()
+}
+
+fn diffs_to_js(diffs: &[Diff]) -> Array {
+    diffs.iter().map(diff_to_js).collect()
+}
+
+/// Read a diff from an [op, text] array, or anything else with the
+/// elements 0 and 1 like the Diff class of newer JavaScript releases.
+fn diff_from_js(value: &JsValue) -> Result<Diff, JsValue> {
+    let op = Reflect::get(value, &JsValue::from(0))?
+        .as_f64()
+        .ok_or_else(|| type_error("diff operation must be a number"))?;
+    let text = Reflect::get(value, &JsValue::from(1))?
+        .as_string()
+        .ok_or_else(|| type_error("diff text must be a string"))?;
+    Diff::from_op(op as i32, text).ok_or_else(|| type_error("unknown diff operation"))
+}
+
+fn diffs_from_js(value: &JsValue) -> Result<Vec<Diff>, JsValue> {
+    let array = value
+        .dyn_ref::<Array>()
+        .ok_or_else(|| type_error("diffs must be an array"))?;
+    array.iter().map(|diff| diff_from_js(&diff)).collect()
+}
+
+fn patch_to_js(patch: &Patch) -> Result<JsValue, JsValue> {
+    let object = Object::new();
+    Reflect::set(&object, &"diffs".into(), &diffs_to_js(&patch.diffs))?;
+    Reflect::set(&object, &"start1".into(), &(patch.start1 as f64).into())?;
+    Reflect::set(&object, &"start2".into(), &(patch.start2 as f64).into())?;
+    Reflect::set(&object, &"length1".into(), &(patch.length1 as f64).into())?;
+    Reflect::set(&object, &"length2".into(), &(patch.length2 as f64).into())?;
+    Ok(object.into())
+}
+
+fn patches_to_js(patches: &[Patch]) -> Result<Array, JsValue> {
+    patches.iter().map(patch_to_js).collect()
+}
+
+fn patch_from_js(value: &JsValue) -> Result<Patch, JsValue> {
+    let number = |name: &str| -> Result<usize, JsValue> {
+        Reflect::get(value, &name.into())?
+            .as_f64()
+            .map(|number| number as usize)
+            .ok_or_else(|| type_error(&format!("patch {name} must be a number")))
+    };
+    Ok(Patch::new(
+        diffs_from_js(&Reflect::get(value, &"diffs".into())?)?,
+        number("start1")?,
+        number("start2")?,
+        number("length1")?,
+        number("length2")?,
+    ))
+}
+
+fn patches_from_js(value: &JsValue) -> Result<Vec<Patch>, JsValue> {
+    let array = value
+        .dyn_ref::<Array>()
+        .ok_or_else(|| type_error("patches must be an array"))?;
+    array.iter().map(|patch| patch_from_js(&patch)).collect()
+}
+
+/// The diff_match_patch class of the JavaScript port.
+#[wasm_bindgen(js_name = diff_match_patch)]
+pub struct DiffMatchPatch {
+    dmp: Dmp,
+}
+
+impl Default for DiffMatchPatch {
+    fn default() -> Self {
+        // The defaults of the JavaScript port.
+        DiffMatchPatch {
+            dmp: Dmp {
+                diff_timeout: Some(Duration::from_secs(1)),
+                edit_cost: 4,
+                ..Dmp::default()
+            },
+        }
+    }
+}
+
+#[wasm_bindgen(js_class = diff_match_patch)]
+impl DiffMatchPatch {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> DiffMatchPatch {
+        DiffMatchPatch::default()
+    }
+
+    /// Number of seconds to map a diff before giving up (0 for infinity).
+    #[wasm_bindgen(getter = Diff_Timeout)]
+    pub fn diff_timeout(&self) -> f64 {
+        self.dmp
+            .diff_timeout
+            .map_or(0.0, |timeout| timeout.as_secs_f64())
+    }
+
+    #[wasm_bindgen(setter = Diff_Timeout)]
+    pub fn set_diff_timeout(&mut self, seconds: f64) {
+        self.dmp.diff_timeout = if seconds > 0.0 {
+            Duration::try_from_secs_f64(seconds).ok()
+        } else {
+            None
+        };
+    }
+
+    #[wasm_bindgen(getter = Diff_EditCost)]
+    pub fn edit_cost(&self) -> usize {
+        self.dmp.edit_cost
+    }
+
+    #[wasm_bindgen(setter = Diff_EditCost)]
+    pub fn set_edit_cost(&mut self, edit_cost: usize) {
+        self.dmp.edit_cost = edit_cost;
+    }
+
+    #[wasm_bindgen(getter = Match_Threshold)]
+    pub fn match_threshold(&self) -> f32 {
+        self.dmp.match_threshold
+    }
+
+    #[wasm_bindgen(setter = Match_Threshold)]
+    pub fn set_match_threshold(&mut self, match_threshold: f32) {
+        self.dmp.match_threshold = match_threshold;
+    }
+
+    #[wasm_bindgen(getter = Match_Distance)]
+    pub fn match_distance(&self) -> usize {
+        self.dmp.match_distance
+    }
+
+    #[wasm_bindgen(setter = Match_Distance)]
+    pub fn set_match_distance(&mut self, match_distance: usize) {
+        self.dmp.match_distance = match_distance;
+    }
+
+    #[wasm_bindgen(getter = Patch_DeleteThreshold)]
+    pub fn patch_delete_threshold(&self) -> f32 {
+        self.dmp.patch_delete_threshold
+    }
+
+    #[wasm_bindgen(setter = Patch_DeleteThreshold)]
+    pub fn set_patch_delete_threshold(&mut self, patch_delete_threshold: f32) {
+        self.dmp.patch_delete_threshold = patch_delete_threshold;
+    }
+
+    #[wasm_bindgen(getter = Patch_Margin)]
+    pub fn patch_margin(&self) -> usize {
+        self.dmp.patch_margin
+    }
+
+    #[wasm_bindgen(setter = Patch_Margin)]
+    pub fn set_patch_margin(&mut self, patch_margin: usize) {
+        self.dmp.patch_margin = patch_margin;
+    }
+
+    #[wasm_bindgen(getter = Match_MaxBits)]
+    pub fn match_maxbits(&self) -> usize {
+        self.dmp.match_maxbits
+    }
+
+    #[wasm_bindgen(setter = Match_MaxBits)]
+    pub fn set_match_maxbits(&mut self, match_maxbits: usize) {
+        self.dmp.match_maxbits = match_maxbits;
+    }
+
+    /// Find the differences between two texts, as [op, text] arrays.
+    pub fn diff_main(&self, text1: &str, text2: &str, opt_checklines: Option<bool>) -> Array {
+        diffs_to_js(
+            &self
+                .dmp
+                .diff_main(text1, text2, opt_checklines.unwrap_or(true)),
+        )
+    }
+
+    /// Reduce the number of edits by eliminating semantically trivial
+    /// equalities.  The array is modified in place.
+    #[wasm_bindgen(js_name = diff_cleanupSemantic)]
+    pub fn diff_cleanup_semantic(&self, diffs: &Array) -> Result<(), JsValue> {
+        let mut cleaned = diffs_from_js(diffs)?;
+        self.dmp.diff_cleanup_semantic(&mut cleaned);
+        diffs.set_length(0);
+        for diff in &cleaned {
+            diffs.push(&diff_to_js(diff));
+        }
+        Ok(())
+    }
+
+    /// Compute a list of patches from two texts, from a diff, or from the
+    /// old text and a diff, like the overloads of the JavaScript port.
+    pub fn patch_make(&self, a: JsValue, opt_b: JsValue, opt_c: JsValue) -> Result<Array, JsValue> {
+        let patches = match (a.as_string(), opt_b.as_string()) {
+            (Some(text1), Some(text2)) if opt_c.is_undefined() => {
+                self.dmp.patch_make1_u16(&text1, &text2)
+            }
+            (Some(text1), Some(_)) => self
+                .dmp
+                .patch_make4_u16(&text1, &mut diffs_from_js(&opt_c)?),
+            (Some(text1), None) => self
+                .dmp
+                .patch_make4_u16(&text1, &mut diffs_from_js(&opt_b)?),
+            (None, _) => self.dmp.patch_make2_u16(&mut diffs_from_js(&a)?),
+        };
+        patches_to_js(&patches)
+    }
+
+    /// Merge patches onto a text.  Returns [text, results].
+    pub fn patch_apply(&self, patches: &JsValue, text: &str) -> Result<Array, JsValue> {
+        let mut patches = patches_from_js(patches)?;
+        let (text, results) = self.dmp.patch_apply_u16(&mut patches, text);
+        let results: Array = results.into_iter().map(JsValue::from).collect();
+        Ok(Array::of2(
+            &JsValue::from(text.into_iter().collect::<String>()),
+            &results,
+        ))
+    }
+
+    #[wasm_bindgen(js_name = patch_toText)]
+    pub fn patch_to_text(&self, patches: &JsValue) -> Result<String, JsValue> {
+        Ok(self.dmp.patch_to_text_u16(&mut patches_from_js(patches)?))
+    }
+
+    /// Parse a textual representation of patches.  Throws an Error on
+    /// malformed input.
+    #[wasm_bindgen(js_name = patch_fromText)]
+    pub fn patch_from_text(&self, textline: &str) -> Result<Array, JsValue> {
+        let patches = self
+            .dmp
+            .patch_parse_unit(textline, super::dmp::LengthUnit::UTF16)
+            .map_err(|err| JsValue::from(Error::new(&err.to_string())))?;
+        patches_to_js(&patches)
+    }
+
+    /// Locate the best instance of pattern in text near loc, -1 if none.
+    pub fn match_main(&self, text: &str, pattern: &str, loc: i32) -> i32 {
+        self.dmp.match_main_u16(text, pattern, loc)
+    }
+}
@@ -0,0 +1,59 @@
+//! Needs `--features async`.
+#![cfg(feature = "async")]
+
+use std::time::{Duration, Instant};
+
+use rusty_diff_match_patch::Dmp;
+
+/// Two unrelated texts over a small alphabet, which take bisect seconds.
+fn slow_texts() -> (String, String) {
+    let mut seed: u32 = 1;
+    let mut text = |len: usize| -> String {
+        (0..len)
+            .map(|_| {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                (b'a' + (seed >> 16) as u8 % 4) as char
+            })
+            .collect()
+    };
+    (text(20000), text(20000))
+}
+
+#[tokio::test]
+pub async fn test_diff_main_async() {
+    let dmp = Dmp::default();
+    let text1 = "The quick brown fox jumps over the lazy dog.";
+    let text2 = "That quick brown fox jumped over a lazy dog.";
+    assert_eq!(
+        dmp.diff_main(text1, text2, false),
+        dmp.diff_main_async(text1, text2, false).await
+    );
+    assert_eq!(
+        dmp.diff_main("", "abc", true),
+        dmp.diff_main_async("", "abc", true).await
+    );
+}
+
+#[test]
+pub fn test_diff_main_async_cancel() {
+    // No timeout, so only the cancellation can end the diff early.
+    let dmp = Dmp {
+        diff_timeout: None,
+        ..Dmp::default()
+    };
+    let (text1, text2) = slow_texts();
+    let start = Instant::now();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let result = runtime.block_on(async {
+        tokio::time::timeout(
+            Duration::from_millis(50),
+            dmp.diff_main_async(&text1, &text2, false),
+        )
+        .await
+    });
+    assert!(result.is_err());
+    // Dropping the runtime waits for the blocking task, which has to notice
+    // the cancellation instead of finishing the diff.
+    drop(runtime);
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
@@ -0,0 +1,102 @@
+/* Exercises the C ABI: diff_main, patch_make and patch_apply.
+ *
+ *   cargo build --features ffi
+ *   cc -Iinclude tests/c/test_ffi.c -Ltarget/debug -lrusty_diff_match_patch -o test_ffi
+ *   LD_LIBRARY_PATH=target/debug ./test_ffi
+ */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "rusty_diff_match_patch.h"
+
+static int failures = 0;
+
+#define CHECK(cond)                                                   \
+  do {                                                                \
+    if (!(cond)) {                                                    \
+      fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, \
+              #cond);                                                 \
+      failures++;                                                     \
+    }                                                                 \
+  } while (0)
+
+static int text_is(DmpString text, const char *expected) {
+  return text.len == strlen(expected) && memcmp(text.ptr, expected, text.len) == 0 &&
+         text.ptr[text.len] == '\0';
+}
+
+static void test_diff_main(const Dmp *dmp) {
+  const char *text1 = "abc";
+  const char *text2 = "ab123c";
+  DmpDiffs diffs;
+  CHECK(dmp_diff_main(dmp, text1, strlen(text1), text2, strlen(text2), false, &diffs) ==
+        DMP_STATUS_OK);
+  CHECK(diffs.len == 3);
+  if (diffs.len == 3) {
+    CHECK(diffs.ptr[0].op == 0 && text_is(diffs.ptr[0].text, "ab"));
+    CHECK(diffs.ptr[1].op == 1 && text_is(diffs.ptr[1].text, "123"));
+    CHECK(diffs.ptr[2].op == 0 && text_is(diffs.ptr[2].text, "c"));
+  }
+  dmp_diffs_free(diffs);
+
+  /* Multi-byte UTF-8 comes back byte for byte. */
+  const char *text3 = "caf\xc3\xa9";
+  const char *text4 = "caf\xc3\xa8";
+  CHECK(dmp_diff_main(dmp, text3, strlen(text3), text4, strlen(text4), false, &diffs) ==
+        DMP_STATUS_OK);
+  CHECK(diffs.len == 3);
+  if (diffs.len == 3) {
+    CHECK(diffs.ptr[0].op == 0 && text_is(diffs.ptr[0].text, "caf"));
+    CHECK(diffs.ptr[1].op == -1 && text_is(diffs.ptr[1].text, "\xc3\xa9"));
+    CHECK(diffs.ptr[2].op == 1 && text_is(diffs.ptr[2].text, "\xc3\xa8"));
+  }
+  dmp_diffs_free(diffs);
+
+  /* Invalid UTF-8 and NULL pointers are rejected. */
+  CHECK(dmp_diff_main(dmp, "\xff", 1, "", 0, false, &diffs) == DMP_STATUS_INVALID_ARGUMENT);
+  CHECK(dmp_diff_main(NULL, "", 0, "", 0, false, &diffs) == DMP_STATUS_INVALID_ARGUMENT);
+  CHECK(dmp_diff_main(dmp, "", 0, "", 0, false, NULL) == DMP_STATUS_INVALID_ARGUMENT);
+}
+
+static void test_patch_apply(const Dmp *dmp) {
+  const char *text1 = "The quick brown fox jumps over the lazy dog.";
+  const char *text2 = "That quick brown fox jumped over a lazy dog.";
+  DmpString patch_text;
+  CHECK(dmp_patch_make(dmp, text1, strlen(text1), text2, strlen(text2), &patch_text) ==
+        DMP_STATUS_OK);
+  CHECK(text_is(patch_text,
+                "@@ -1,11 +1,12 @@\n Th\n-e\n+at\n  quick b\n"
+                "@@ -22,18 +22,17 @@\n jump\n-s\n+ed\n  over \n-the\n+a\n  laz\n"));
+
+  const char *text3 = "The quick red rabbit jumps over the tired tiger.";
+  DmpApplyResult result;
+  CHECK(dmp_patch_apply(dmp, patch_text.ptr, patch_text.len, text3, strlen(text3), &result) ==
+        DMP_STATUS_OK);
+  CHECK(text_is(result.text, "That quick red rabbit jumped over a tired tiger."));
+  CHECK(result.len == 2);
+  if (result.len == 2) {
+    CHECK(result.results[0] && result.results[1]);
+  }
+  dmp_apply_result_free(result);
+  dmp_string_free(patch_text);
+
+  const char *bad = "Bad\nPatch\n";
+  CHECK(dmp_patch_apply(dmp, bad, strlen(bad), text3, strlen(text3), &result) ==
+        DMP_STATUS_PARSE_ERROR);
+}
+
+int main(void) {
+  Dmp *dmp = dmp_new();
+  dmp_set_diff_timeout(dmp, 0.0);
+  test_diff_main(dmp);
+  test_patch_apply(dmp);
+  dmp_free(dmp);
+  if (failures != 0) {
+    fprintf(stderr, "%d checks failed\n", failures);
+    return EXIT_FAILURE;
+  }
+  printf("ok\n");
+  return EXIT_SUCCESS;
+}
@@ -0,0 +1,235 @@
+// Regenerate golden/java.json from the reference Java port.
+//
+//     git clone https://github.com/google/diff-match-patch
+//     javac -d /tmp/dmp-java \
+//         diff-match-patch/java/src/name/fraser/neil/plaintext/diff_match_patch.java \
+//         tests/conformance/Generate.java
+//     java -cp /tmp/dmp-java Generate tests/conformance
+//
+// Offsets of the Java port count UTF-16 code units, so the golden file is
+// checked with LengthUnit::UTF16.  Inputs may override a field for UTF-16
+// with a "<field>_utf16" key.  The JDK has no JSON library, so a small
+// reader and writer for the subset cases.json uses are included.
+
+import java.io.IOException;
+import java.nio.charset.StandardCharsets;
+import java.nio.file.Files;
+import java.nio.file.Path;
+import java.nio.file.Paths;
+import java.util.ArrayList;
+import java.util.LinkedHashMap;
+import java.util.LinkedList;
+import java.util.List;
+import java.util.Map;
+
+import name.fraser.neil.plaintext.diff_match_patch;
+
+public class Generate {
+  private final String text;
+  private int pos = 0;
+
+  private Generate(String text) {
+    this.text = text;
+  }
+
+  private void skipSpace() {
+    while (pos < text.length() && Character.isWhitespace(text.charAt(pos))) {
+      pos++;
+    }
+  }
+
+  private void expect(char ch) {
+    skipSpace();
+    if (text.charAt(pos) != ch) {
+      throw new IllegalArgumentException("expected " + ch + " at " + pos);
+    }
+    pos++;
+  }
+
+  private Object parse() {
+    skipSpace();
+    char ch = text.charAt(pos);
+    if (ch == '{') {
+      Map<String, Object> object = new LinkedHashMap<>();
+      pos++;
+      skipSpace();
+      if (text.charAt(pos) == '}') {
+        pos++;
+        return object;
+      }
+      do {
+        skipSpace();
+        String key = parseString();
+        expect(':');
+        object.put(key, parse());
+        skipSpace();
+      } while (text.charAt(pos++) == ',');
+      return object;
+    }
+    if (ch == '[') {
+      List<Object> array = new ArrayList<>();
+      pos++;
+      skipSpace();
+      if (text.charAt(pos) == ']') {
+        pos++;
+        return array;
+      }
+      do {
+        array.add(parse());
+        skipSpace();
+      } while (text.charAt(pos++) == ',');
+      return array;
+    }
+    if (ch == '"') {
+      return parseString();
+    }
+    int start = pos;
+    while (pos < text.length() && "+-.0123456789eE".indexOf(text.charAt(pos)) != -1) {
+      pos++;
+    }
+    String number = text.substring(start, pos);
+    if (number.isEmpty()) {
+      throw new IllegalArgumentException("unexpected " + ch + " at " + pos);
+    }
+    return number.matches("-?\\d+") ? (Object) Long.valueOf(number) : Double.valueOf(number);
+  }
+
+  private String parseString() {
+    expect('"');
+    StringBuilder builder = new StringBuilder();
+    while (true) {
+      char ch = text.charAt(pos++);
+      if (ch == '"') {
+        return builder.toString();
+      }
+      if (ch != '\\') {
+        builder.append(ch);
+        continue;
+      }
+      ch = text.charAt(pos++);
+      switch (ch) {
+        case 'n': builder.append('\n'); break;
+        case 't': builder.append('\t'); break;
+        case 'r': builder.append('\r'); break;
+        case 'b': builder.append('\b'); break;
+        case 'f': builder.append('\f'); break;
+        case 'u':
+          builder.append((char) Integer.parseInt(text.substring(pos, pos + 4), 16));
+          pos += 4;
+          break;
+        default: builder.append(ch);
+      }
+    }
+  }
+
+  private static String quote(String value) {
+    StringBuilder builder = new StringBuilder("\"");
+    for (char ch : value.toCharArray()) {
+      switch (ch) {
+        case '"': builder.append("\\\""); break;
+        case '\\': builder.append("\\\\"); break;
+        case '\n': builder.append("\\n"); break;
+        case '\t': builder.append("\\t"); break;
+        case '\r': builder.append("\\r"); break;
+        default:
+          if (ch < 0x20) {
+            builder.append(String.format("\\u%04x", (int) ch));
+          } else {
+            builder.append(ch);
+          }
+      }
+    }
+    return builder.append('"').toString();
+  }
+
+  @SuppressWarnings("unchecked")
+  private static String dump(Object value) {
+    if (value instanceof String) {
+      return quote((String) value);
+    }
+    if (value instanceof Map) {
+      List<String> items = new ArrayList<>();
+      for (Map.Entry<String, Object> entry : ((Map<String, Object>) value).entrySet()) {
+        items.add(quote(entry.getKey()) + ": " + dump(entry.getValue()));
+      }
+      return "{" + String.join(", ", items) + "}";
+    }
+    if (value instanceof List) {
+      List<String> items = new ArrayList<>();
+      for (Object item : (List<Object>) value) {
+        items.add(dump(item));
+      }
+      return "[" + String.join(", ", items) + "]";
+    }
+    return String.valueOf(value);
+  }
+
+  @SuppressWarnings("unchecked")
+  private static Object run(Map<String, Object> c) {
+    diff_match_patch dmp = new diff_match_patch();
+    if (c.containsKey("match_threshold")) {
+      dmp.Match_Threshold = ((Number) c.get("match_threshold")).floatValue();
+    }
+    if (c.containsKey("match_distance")) {
+      dmp.Match_Distance = ((Number) c.get("match_distance")).intValue();
+    }
+    String fn = (String) c.get("fn");
+    switch (fn) {
+      case "diff_todelta": {
+        LinkedList<diff_match_patch.Diff> diffs = new LinkedList<>();
+        for (Object item : (List<Object>) c.get("diffs")) {
+          List<Object> diff = (List<Object>) item;
+          long op = (Long) diff.get(0);
+          diff_match_patch.Operation operation = op < 0
+              ? diff_match_patch.Operation.DELETE
+              : op > 0 ? diff_match_patch.Operation.INSERT : diff_match_patch.Operation.EQUAL;
+          diffs.add(new diff_match_patch.Diff(operation, (String) diff.get(1)));
+        }
+        return dmp.diff_toDelta(diffs);
+      }
+      case "patch_make":
+        return dmp.patch_toText(dmp.patch_make((String) c.get("text1"), (String) c.get("text2")));
+      case "patch_from_text":
+        return dmp.patch_toText(dmp.patch_fromText((String) c.get("text")));
+      case "match_main":
+        return (long) dmp.match_main(
+            (String) c.get("text"), (String) c.get("pattern"), ((Long) c.get("loc")).intValue());
+      default:
+        throw new IllegalArgumentException("unknown function " + fn);
+    }
+  }
+
+  @SuppressWarnings("unchecked")
+  public static void main(String[] args) throws IOException {
+    Path here = Paths.get(args.length > 0 ? args[0] : ".");
+    String input = new String(Files.readAllBytes(here.resolve("cases.json")), StandardCharsets.UTF_8);
+    Map<String, Object> root = (Map<String, Object>) new Generate(input).parse();
+
+    StringBuilder out = new StringBuilder("{\n");
+    out.append(" \"implementation\": \"java\",\n");
+    out.append(" \"length_unit\": \"utf-16\",\n");
+    out.append(" \"source\": \"diff-match-patch Java port, Generate.java\",\n");
+    out.append(" \"cases\": [\n");
+    List<String> golden = new ArrayList<>();
+    for (Object item : (List<Object>) root.get("cases")) {
+      Map<String, Object> inputCase = (Map<String, Object>) item;
+      Map<String, Object> c = new LinkedHashMap<>();
+      for (Map.Entry<String, Object> entry : inputCase.entrySet()) {
+        if (!entry.getKey().endsWith("_utf16")) {
+          c.put(entry.getKey(), entry.getValue());
+        }
+      }
+      for (Map.Entry<String, Object> entry : inputCase.entrySet()) {
+        String key = entry.getKey();
+        if (key.endsWith("_utf16")) {
+          c.put(key.substring(0, key.length() - "_utf16".length()), entry.getValue());
+        }
+      }
+      c.put("expected", run(c));
+      golden.add("  " + dump(c));
+    }
+    out.append(String.join(",\n", golden));
+    out.append("\n ]\n}\n");
+    Files.write(here.resolve("golden").resolve("java.json"), out.toString().getBytes(StandardCharsets.UTF_8));
+  }
+}
@@ -0,0 +1,73 @@
+#!/usr/bin/env node
+// Regenerate golden/javascript.json from the reference JavaScript port.
+//
+//     npm install diff-match-patch
+//     node tests/conformance/generate.js
+//
+// Offsets of the JavaScript port count UTF-16 code units, so the golden file
+// is checked with LengthUnit::UTF16.  Inputs may override a field for UTF-16
+// with a "<field>_utf16" key.
+
+'use strict';
+
+const fs = require('fs');
+const path = require('path');
+const DiffMatchPatch = require('diff-match-patch');
+
+const version = require('diff-match-patch/package.json').version;
+
+function makeDiff(op, text) {
+  // Newer releases wrap diffs in a class, older ones use plain arrays.
+  return DiffMatchPatch.Diff ? new DiffMatchPatch.Diff(op, text) : [op, text];
+}
+
+function run(c) {
+  const dmp = new DiffMatchPatch();
+  if ('match_threshold' in c) dmp.Match_Threshold = c.match_threshold;
+  if ('match_distance' in c) dmp.Match_Distance = c.match_distance;
+  switch (c.fn) {
+    case 'diff_todelta':
+      return dmp.diff_toDelta(c.diffs.map(([op, text]) => makeDiff(op, text)));
+    case 'patch_make':
+      return dmp.patch_toText(dmp.patch_make(c.text1, c.text2));
+    case 'patch_from_text':
+      return dmp.patch_toText(dmp.patch_fromText(c.text));
+    case 'match_main':
+      return dmp.match_main(c.text, c.pattern, c.loc);
+    default:
+      throw new Error('unknown function ' + c.fn);
+  }
+}
+
+function dump(file, head, cases) {
+  let text = '{\n';
+  for (const [key, value] of Object.entries(head)) {
+    text += ` ${JSON.stringify(key)}: ${JSON.stringify(value)},\n`;
+  }
+  text += ' "cases": [\n';
+  text += cases.map((c) => '  ' + JSON.stringify(c)).join(',\n');
+  text += '\n ]\n}\n';
+  fs.writeFileSync(file, text);
+}
+
+const cases = JSON.parse(fs.readFileSync(path.join(__dirname, 'cases.json'), 'utf8')).cases;
+const golden = cases.map((input) => {
+  const c = {};
+  for (const [key, value] of Object.entries(input)) {
+    if (!key.endsWith('_utf16')) c[key] = value;
+  }
+  for (const [key, value] of Object.entries(input)) {
+    if (key.endsWith('_utf16')) c[key.slice(0, -'_utf16'.length)] = value;
+  }
+  c.expected = run(c);
+  return c;
+});
+dump(
+  path.join(__dirname, 'golden', 'javascript.json'),
+  {
+    implementation: 'javascript',
+    length_unit: 'utf-16',
+    source: `diff-match-patch ${version}, generate.js`,
+  },
+  golden,
+);
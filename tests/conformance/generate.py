@@ -0,0 +1,68 @@
+#!/usr/bin/env python3
+"""Regenerate golden/python.json from the reference Python port.
+
+    pip install diff-match-patch
+    python3 tests/conformance/generate.py
+
+Offsets of the Python port count Unicode code points, so the golden file is
+checked with LengthUnit::UnicodeScalar.
+"""
+
+import json
+import os
+
+import diff_match_patch
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+
+
+def run(case):
+    dmp = diff_match_patch.diff_match_patch()
+    if "match_threshold" in case:
+        dmp.Match_Threshold = case["match_threshold"]
+    if "match_distance" in case:
+        dmp.Match_Distance = case["match_distance"]
+    fn = case["fn"]
+    if fn == "diff_todelta":
+        return dmp.diff_toDelta([tuple(diff) for diff in case["diffs"]])
+    if fn == "patch_make":
+        return dmp.patch_toText(dmp.patch_make(case["text1"], case["text2"]))
+    if fn == "patch_from_text":
+        return dmp.patch_toText(dmp.patch_fromText(case["text"]))
+    if fn == "match_main":
+        return dmp.match_main(case["text"], case["pattern"], case["loc"])
+    raise ValueError("unknown function " + fn)
+
+
+def dump(path, head, cases):
+    with open(path, "w", encoding="utf-8") as f:
+        f.write("{\n")
+        for key, value in head.items():
+            f.write(" %s: %s,\n" % (json.dumps(key), json.dumps(value, ensure_ascii=False)))
+        f.write(' "cases": [\n')
+        f.write(",\n".join("  " + json.dumps(c, ensure_ascii=False) for c in cases))
+        f.write("\n ]\n}\n")
+
+
+def main():
+    with open(os.path.join(HERE, "cases.json"), encoding="utf-8") as f:
+        cases = json.load(f)["cases"]
+    golden = []
+    for case in cases:
+        case = {key: value for key, value in case.items() if not key.endswith("_utf16")}
+        case["expected"] = run(case)
+        golden.append(case)
+    version = getattr(diff_match_patch, "__version__", "unknown")
+    dump(
+        os.path.join(HERE, "golden", "python.json"),
+        {
+            "implementation": "python",
+            "length_unit": "scalar",
+            "source": "diff-match-patch %s, generate.py" % version,
+        },
+        golden,
+    )
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,44 @@
+//! Needs `--features dictionary`.
+#![cfg(feature = "dictionary")]
+
+use rusty_diff_match_patch::{BoundaryScoring, Diff, Dmp};
+
+#[test]
+pub fn test_diff_words_dictionary() {
+    let dmp = Dmp::default();
+    assert_eq!(
+        vec![
+            Diff::Keep("我们学习".to_string()),
+            Diff::Add("中国历史和".to_string()),
+            Diff::Keep("中文。".to_string())
+        ],
+        dmp.diff_words("我们学习中文。", "我们学习中国历史和中文。")
+    );
+    assert_eq!(
+        vec![
+            Diff::Keep("我们".to_string()),
+            Diff::Delete("今天".to_string()),
+            Diff::Add("明天".to_string()),
+            Diff::Keep("去北京。".to_string())
+        ],
+        dmp.diff_words("我们今天去北京。", "我们明天去北京。")
+    );
+}
+
+#[test]
+pub fn test_diff_cleanup_dictionary_scoring() {
+    let dmp = Dmp {
+        boundary_scoring: BoundaryScoring::Unicode,
+        ..Dmp::default()
+    };
+    let mut diffs = dmp.diff_main("我们学习中文。", "我们学习中国历史和中文。", false);
+    dmp.diff_cleanup_semantic(&mut diffs);
+    assert_eq!(
+        vec![
+            Diff::Keep("我们学习".to_string()),
+            Diff::Add("中国历史和".to_string()),
+            Diff::Keep("中文。".to_string())
+        ],
+        diffs
+    );
+}
@@ -0,0 +1,68 @@
+//! Builds tests/c/test_ffi.c with the system C compiler against the cdylib
+//! and runs it.  Needs `--features ffi`; the crate is an rlib unless asked
+//! otherwise, so the cdylib is built here first with `cargo rustc`.
+#![cfg(feature = "ffi")]
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[test]
+pub fn test_c_program() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    // The test binary lives in target/<profile>/deps, next to the cdylib.
+    let lib_dir: PathBuf = env::current_exe()
+        .unwrap()
+        .parent()
+        .and_then(Path::parent)
+        .unwrap()
+        .to_path_buf();
+    let program = lib_dir.join("test_ffi");
+
+    let mut cargo = Command::new(env!("CARGO"));
+    cargo
+        .args([
+            "rustc",
+            "--lib",
+            "--crate-type",
+            "cdylib",
+            "--features",
+            "ffi",
+        ])
+        .arg("--manifest-path")
+        .arg(root.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(lib_dir.parent().unwrap());
+    if !cfg!(debug_assertions) {
+        cargo.arg("--release");
+    }
+    let status = cargo.status().expect("failed to run cargo");
+    assert!(status.success(), "building the cdylib failed");
+
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+
+    let status = Command::new(cc)
+        .args(["-Wall", "-Wextra", "-Werror", "-std=c99", "-I"])
+        .arg(root.join("include"))
+        .arg(root.join("tests/c/test_ffi.c"))
+        .arg("-L")
+        .arg(&lib_dir)
+        .arg("-lrusty_diff_match_patch")
+        .arg("-o")
+        .arg(&program)
+        .status()
+        .expect("failed to run the C compiler");
+    assert!(status.success(), "compiling tests/c/test_ffi.c failed");
+
+    let output = Command::new(&program)
+        .env("LD_LIBRARY_PATH", &lib_dir)
+        .env("DYLD_LIBRARY_PATH", &lib_dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+}
@@ -0,0 +1,31 @@
+//! Checks that include/rusty_diff_match_patch.h is what cbindgen generates
+//! from src/ffi.rs with cbindgen.toml.  Run with CBINDGEN_UPDATE=1 to write
+//! the header instead.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+#[test]
+pub fn test_header_up_to_date() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let config = cbindgen::Config::from_file(root.join("cbindgen.toml")).unwrap();
+    let mut generated = vec![];
+    cbindgen::Builder::new()
+        .with_crate(root)
+        .with_config(config)
+        .generate()
+        .expect("cbindgen failed")
+        .write(&mut generated);
+    let generated = String::from_utf8(generated).unwrap();
+
+    let path = root.join("include/rusty_diff_match_patch.h");
+    if env::var_os("CBINDGEN_UPDATE").is_some() {
+        fs::write(&path, &generated).unwrap();
+    }
+    assert!(
+        fs::read_to_string(&path).unwrap() == generated,
+        "{} is out of date, rerun this test with CBINDGEN_UPDATE=1",
+        path.display()
+    );
+}
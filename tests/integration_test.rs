@@ -1,6 +1,16 @@
-use rusty_diff_match_patch::{Diff, Dmp, LengthUnit, Patch};
+use proptest::prelude::*;
+use rusty_diff_match_patch::{
+    text_hash, ApplyDirError, BlameLine, BoundaryScorer, BoundaryScoring, ConfigError, Diff,
+    DiffStats, Dmp, DmpBuilder, FilePatch, FsStorage, HistoryError, IgnoreSpace, InMemoryTransport,
+    LengthUnit, MemoryStorage, Move, Normalization, Patch, PatchSet, PatchSetError, Revision,
+    RevisionStorage, RevisionStore, Row, RowKind, SimilarPair, SyncClient, SyncEdit, SyncError,
+    SyncMessage, SyncServer,
+};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
+#[allow(clippy::needless_range_loop)]
 pub fn diff_rebuildtexts(diffs: Vec<Diff>) -> Vec<String> {
     let mut text1: String = "".to_string();
     let mut text2: String = "".to_string();
@@ -111,7 +121,7 @@ pub fn test_diff_common_overlap() {
 #[test]
 pub fn test_diff_half_match() {
     let dmp = Dmp {
-        diff_timeout: Some(1.0),
+        diff_timeout: Some(Duration::from_secs(1)),
         ..Default::default()
     };
     let temp: Option<[String; 5]> = None;
@@ -261,6 +271,7 @@ pub fn test_diff_half_match_no_timeout() {
 }
 
 #[test]
+#[allow(clippy::single_match, clippy::useless_vec)]
 pub fn test_diff_lines_tochars() {
     let dmp = Dmp::default();
     assert_eq!(
@@ -335,6 +346,7 @@ pub fn test_diff_lines_tochars() {
 }
 
 #[test]
+#[allow(clippy::unnecessary_to_owned)]
 pub fn test_diff_words_tochars() {
     let dmp = Dmp::default();
     assert_eq!(
@@ -394,6 +406,7 @@ pub fn test_diff_words_tochars() {
 }
 
 #[test]
+#[allow(clippy::useless_vec)]
 pub fn test_diff_chars_tolines() {
     let dmp = Dmp::default();
     let mut diffs = vec![
@@ -1071,6 +1084,7 @@ pub fn test_diff_text2_u16() {
 }
 
 #[test]
+#[allow(clippy::unnecessary_to_owned)]
 pub fn test_diff_delta() {
     let dmp = Dmp::default();
     let mut diffs = vec![
@@ -1251,6 +1265,20 @@ pub fn test_diff_from_delta_split_surrogates() {
     );
 }
 
+#[test]
+pub fn test_diff_pretty_html() {
+    let dmp = Dmp::default();
+    let diffs = vec![
+        Diff::Keep("a\n".to_string()),
+        Diff::Delete("<B>b</B>".to_string()),
+        Diff::Add("c&d".to_string()),
+    ];
+    assert_eq!(
+        "<span>a&para;<br></span><del style=\"background:#ffe6e6;\">&lt;B&gt;b&lt;/B&gt;</del><ins style=\"background:#e6ffe6;\">c&amp;d</ins>",
+        dmp.diff_pretty_html(&diffs)
+    );
+}
+
 #[test]
 pub fn test_diff_xindex() {
     let dmp = Dmp::default();
@@ -1270,6 +1298,15 @@ pub fn test_diff_xindex() {
         Diff::Keep("xyz".to_string()),
     ];
     assert_eq!(1, dmp.diff_xindex(&diffs, 3));
+    // Non-ASCII text, in each unit.
+    let diffs = vec![
+        Diff::Keep("🅰".to_string()),
+        Diff::Add("é".to_string()),
+        Diff::Keep("x".to_string()),
+    ];
+    assert_eq!(3, dmp.diff_xindex(&diffs, 2));
+    assert_eq!(4, dmp.diff_xindex_unit(&diffs, 3, LengthUnit::UTF16));
+    assert_eq!(7, dmp.diff_xindex_unit(&diffs, 5, LengthUnit::UTF8));
 }
 
 #[test]
@@ -1301,6 +1338,15 @@ pub fn test_diff_levenshtein() {
             Diff::Add("1234".to_string())
         ])
     );
+    // Lengths count chars, not bytes.
+    let diffs = vec![
+        Diff::Delete("äöü".to_string()),
+        Diff::Add("ß".to_string()),
+        Diff::Keep("🅰".to_string()),
+    ];
+    assert_eq!(3, dmp.diff_levenshtein(&diffs));
+    assert_eq!(6, dmp.diff_levenshtein_unit(&diffs, LengthUnit::UTF8));
+    assert_eq!(3, dmp.diff_levenshtein_unit(&diffs, LengthUnit::UTF16));
 }
 
 #[test]
@@ -1326,7 +1372,7 @@ pub fn test_diff_bisect() {
 #[test]
 pub fn test_diff_bisect_timeout() {
     let dmp = Dmp {
-        diff_timeout: Some(0.0),
+        diff_timeout: Some(Duration::ZERO),
         ..Default::default()
     };
 
@@ -1346,6 +1392,26 @@ pub fn test_diff_bisect_timeout() {
     assert_eq!(expected, result);
 }
 
+#[test]
+pub fn test_diff_main_cancellable() {
+    let dmp = Dmp::default();
+    let cancel = AtomicBool::new(false);
+    assert_eq!(
+        dmp.diff_main("cat", "map", false),
+        dmp.diff_main_cancellable("cat", "map", false, &cancel)
+    );
+
+    // A cancelled diff gives up like one which ran out of time.
+    cancel.store(true, Ordering::Relaxed);
+    assert_eq!(
+        vec![
+            Diff::Delete("cat".to_string()),
+            Diff::Add("map".to_string())
+        ],
+        dmp.diff_main_cancellable("cat", "map", false, &cancel)
+    );
+}
+
 #[test]
 pub fn test_diff_main() {
     let new_dmp = Dmp::default();
@@ -1489,6 +1555,448 @@ pub fn test_diff_main() {
     assert_eq!(texts_linemode, texts_textmode);
 }
 
+#[test]
+pub fn test_diff_main_normalized() {
+    let dmp = Dmp {
+        normalization: Normalization::NFC,
+        ..Default::default()
+    };
+    // Canonically equivalent text aligns, but keeps both spellings.
+    assert_eq!(
+        vec![
+            Diff::Keep("Caf".to_string()),
+            Diff::Delete("\u{e9}".to_string()),
+            Diff::Add("e\u{301}".to_string()),
+            Diff::Keep(" au ".to_string()),
+            Diff::Delete("lait".to_string()),
+            Diff::Add("cre\u{300}me".to_string())
+        ],
+        dmp.diff_main("Caf\u{e9} au lait", "Cafe\u{301} au cre\u{300}me", false)
+    );
+
+    let dmp = Dmp {
+        ignore_case: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        vec![
+            Diff::Delete("t".to_string()),
+            Diff::Add("T".to_string()),
+            Diff::Keep("he ".to_string()),
+            Diff::Delete("c".to_string()),
+            Diff::Add("C".to_string()),
+            Diff::Keep("at ".to_string()),
+            Diff::Add("Sat".to_string())
+        ],
+        dmp.diff_main("the cat ", "The Cat Sat", false)
+    );
+    // Patches made from such diffs change the case too.
+    let mut patches = dmp.patch_make1("Hello World foo", "hello world bar");
+    let (text, results) = dmp.patch_apply(&mut patches, "Hello World foo");
+    assert_eq!("hello world bar", text.into_iter().collect::<String>());
+    assert_eq!(vec![true], results);
+
+    let dmp = Dmp {
+        ignore_whitespace: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        vec![
+            Diff::Keep("a".to_string()),
+            Diff::Delete("  b".to_string()),
+            Diff::Add(" c".to_string())
+        ],
+        dmp.diff_main("a  b", "a c", false)
+    );
+    let mut diffs = dmp.diff_main("a  b", "a b c", false);
+    assert_eq!("a  b", dmp.diff_text1(&mut diffs));
+    assert_eq!("a b c", dmp.diff_text2(&mut diffs));
+}
+
+#[test]
+pub fn test_diff_main_normalized_hangul() {
+    // A decomposed syllable is one grapheme and aligns with the composed one.
+    let dmp = Dmp {
+        normalization: Normalization::NFC,
+        ..Default::default()
+    };
+    let nfc = "\u{ac01} \u{d55c}";
+    let nfd = "\u{1100}\u{1161}\u{11a8} \u{1112}\u{1161}\u{11ab}";
+    assert_eq!(
+        vec![
+            Diff::Delete("\u{ac01}".to_string()),
+            Diff::Add("\u{1100}\u{1161}\u{11a8}".to_string()),
+            Diff::Keep(" ".to_string()),
+            Diff::Delete("\u{d55c}".to_string()),
+            Diff::Add("\u{1112}\u{1161}\u{11ab}".to_string())
+        ],
+        dmp.diff_main(nfc, nfd, false)
+    );
+    // Locations count chars of the original text.
+    assert_eq!(4, dmp.match_main(nfd, "\u{d55c}", 0));
+    assert_eq!(0, dmp.match_main(nfc, "\u{1100}\u{1161}\u{11a8}", 0));
+}
+
+#[test]
+pub fn test_diff_main_case_folded() {
+    let dmp = Dmp {
+        ignore_case: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        dmp.normalize_text("STRASSE"),
+        dmp.normalize_text("stra\u{df}e")
+    );
+    assert_eq!(dmp.normalize_text("\u{3a3}"), dmp.normalize_text("\u{3c2}"));
+    // A spelling-only change is one pair whose keys agree.
+    let diffs = dmp.diff_main("STRASSE", "stra\u{df}e", false);
+    assert_eq!(
+        vec![
+            Diff::Delete("STRASSE".to_string()),
+            Diff::Add("stra\u{df}e".to_string())
+        ],
+        diffs
+    );
+    assert_eq!(
+        dmp.normalize_text(diffs[0].text()),
+        dmp.normalize_text(diffs[1].text())
+    );
+    assert_eq!(
+        vec![
+            Diff::Keep("Die S".to_string()),
+            Diff::Delete("TRASSE".to_string()),
+            Diff::Add("tra\u{df}e".to_string()),
+            Diff::Keep(" ist ".to_string()),
+            Diff::Delete("lang".to_string()),
+            Diff::Add("kurz".to_string())
+        ],
+        dmp.diff_main("Die STRASSE ist lang", "Die Stra\u{df}e ist kurz", false)
+    );
+    assert_eq!(4, dmp.match_main("Die Stra\u{df}e", "STRASSE", 0));
+}
+
+proptest! {
+    #[test]
+    fn prop_diff_main_normalized_rebuilds_texts(
+        text1 in "[aA \n\u{e9}]{0,24}",
+        text2 in "[aA \n\u{e9}e\u{301}]{0,24}",
+        checklines: bool,
+    ) {
+        for dmp in [
+            Dmp { ignore_case: true, ..Dmp::default() },
+            Dmp { normalization: Normalization::NFC, ..Dmp::default() },
+            Dmp { ignore_whitespace: true, ..Dmp::default() },
+            Dmp { ignore_space: IgnoreSpace::Change, ..Dmp::default() },
+            Dmp { ignore_blank_lines: true, ..Dmp::default() },
+        ] {
+            let mut diffs = dmp.diff_main(&text1, &text2, checklines);
+            prop_assert_eq!(&text1, &dmp.diff_text1(&mut diffs));
+            prop_assert_eq!(&text2, &dmp.diff_text2(&mut diffs));
+        }
+    }
+}
+
+#[test]
+pub fn test_diff_lines_ignore_space() {
+    let old = "fn main() {\nif x {\ny();\n}\n}\n";
+    let new = "fn main() {\n    if x {\n        y(); \n    }\n}\n";
+
+    let dmp = Dmp::default();
+    assert_eq!(
+        vec![
+            Diff::Keep("fn main() {\n".to_string()),
+            Diff::Delete("if x {\ny();\n}\n".to_string()),
+            Diff::Add("    if x {\n        y(); \n    }\n".to_string()),
+            Diff::Keep("}\n".to_string())
+        ],
+        dmp.diff_lines(old, new)
+    );
+
+    // Reindented lines align, and are replaced one by one.
+    let dmp = Dmp {
+        ignore_space: IgnoreSpace::All,
+        ..Default::default()
+    };
+    assert_eq!(
+        vec![
+            Diff::Keep("fn main() {\n".to_string()),
+            Diff::Delete("if x {\n".to_string()),
+            Diff::Add("    if x {\n".to_string()),
+            Diff::Delete("y();\n".to_string()),
+            Diff::Add("        y(); \n".to_string()),
+            Diff::Delete("}\n".to_string()),
+            Diff::Add("    }\n".to_string()),
+            Diff::Keep("}\n".to_string())
+        ],
+        dmp.diff_lines(old, new)
+    );
+    let mut diffs = dmp.diff_main(old, new, true);
+    assert_eq!(old, dmp.diff_text1(&mut diffs));
+    assert_eq!(new, dmp.diff_text2(&mut diffs));
+    // Hashed lines keep the spelling of each side.
+    let (chars1, chars2, lines) = dmp.diff_lines_tochars(
+        &"a\nb\n".chars().collect::<Vec<char>>(),
+        &" a\nb\n".chars().collect::<Vec<char>>(),
+    );
+    assert_eq!(("\x01\x02", "\x03\x02"), (chars1.as_str(), chars2.as_str()));
+    assert_eq!(vec!["", "a\n", "b\n", " a\n"], lines);
+
+    // Only the amount of whitespace may change.
+    let dmp = Dmp {
+        ignore_space: IgnoreSpace::Change,
+        ..Default::default()
+    };
+    assert_eq!(
+        vec![
+            Diff::Delete("a b\n".to_string()),
+            Diff::Add("ab\n".to_string()),
+            Diff::Delete(" c d\n".to_string()),
+            Diff::Add("  c\td \n".to_string())
+        ],
+        dmp.diff_lines("a b\n c d\n", "ab\n  c\td \n")
+    );
+
+    // Only whitespace at the end of lines is ignored.
+    let dmp = Dmp {
+        ignore_space: IgnoreSpace::AtEol,
+        ..Default::default()
+    };
+    assert_eq!(
+        vec![
+            Diff::Delete("a\n".to_string()),
+            Diff::Add("a\r\n".to_string()),
+            Diff::Delete("b\n".to_string()),
+            Diff::Add(" b\n".to_string()),
+            Diff::Delete("c  ".to_string()),
+            Diff::Add("c".to_string())
+        ],
+        dmp.diff_lines("a\nb\nc  ", "a\r\n b\nc")
+    );
+    // A missing newline at the end is still a change.
+    assert_eq!(
+        vec![Diff::Delete("c\n".to_string()), Diff::Add("c".to_string())],
+        dmp.diff_lines("c\n", "c")
+    );
+
+    // Blank lines do not get in the way of aligning the others.
+    let dmp = Dmp {
+        ignore_blank_lines: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        vec![
+            Diff::Keep("a\n".to_string()),
+            Diff::Add("\n".to_string()),
+            Diff::Keep("b\n".to_string()),
+            Diff::Delete("\n  \nc\n".to_string())
+        ],
+        dmp.diff_lines("a\nb\n\n  \nc\n", "a\n\nb\n")
+    );
+}
+
+#[test]
+pub fn test_diff_moves() {
+    let dmp = Dmp::default();
+    // A line moved to the end.
+    let diffs = dmp.diff_lines("alpha\nbeta\ngamma\n", "beta\ngamma\nalpha\n");
+    assert_eq!(
+        vec![Move {
+            from: 0,
+            to: 2,
+            text: "alpha\n".to_string(),
+            exact: true
+        }],
+        dmp.diff_moves(&diffs, 1)
+    );
+    assert_eq!(Vec::<Move>::new(), dmp.diff_moves(&diffs, 10));
+
+    // A paragraph moved and touched up on the way.
+    let diffs = vec![
+        Diff::Delete("The quick brown fox jumps over the lazy dog.\n".to_string()),
+        Diff::Keep("Lorem ipsum dolor sit amet.\n".to_string()),
+        Diff::Add("The quick brown fox jumped over the lazy dog!\n".to_string()),
+    ];
+    assert_eq!(
+        vec![Move {
+            from: 0,
+            to: 2,
+            text: "The quick brown fox jumps over the lazy dog.\n".to_string(),
+            exact: false
+        }],
+        dmp.diff_moves(&diffs, 1)
+    );
+
+    // An exact copy of a later deletion is not taken by a fuzzy match of an
+    // earlier one.
+    let diffs = vec![
+        Diff::Delete("The quick brown fox jumps over the lazy dog.\n".to_string()),
+        Diff::Keep("Lorem ipsum dolor sit amet.\n".to_string()),
+        Diff::Delete("The quick brown fox jumped over the lazy dog!\n".to_string()),
+        Diff::Keep("Consectetur adipiscing elit.\n".to_string()),
+        Diff::Add("The quick brown fox jumped over the lazy dog!\n".to_string()),
+    ];
+    assert_eq!(
+        vec![Move {
+            from: 2,
+            to: 4,
+            text: "The quick brown fox jumped over the lazy dog!\n".to_string(),
+            exact: true
+        }],
+        dmp.diff_moves(&diffs, 1)
+    );
+
+    // Replacements are not moves.
+    let diffs = vec![
+        Diff::Keep("a".to_string()),
+        Diff::Delete("xyz".to_string()),
+        Diff::Add("xyz!".to_string()),
+        Diff::Keep("b".to_string()),
+    ];
+    assert_eq!(Vec::<Move>::new(), dmp.diff_moves(&diffs, 1));
+}
+
+#[test]
+pub fn test_diff_split_lines() {
+    let dmp = Dmp::default();
+    assert_eq!(
+        vec![
+            Diff::Keep("a\n".to_string()),
+            Diff::Delete("b\n".to_string()),
+            Diff::Delete("c".to_string()),
+            Diff::Add("d\n".to_string())
+        ],
+        dmp.diff_split_lines(&[
+            Diff::Keep("a\n".to_string()),
+            Diff::Delete("b\nc".to_string()),
+            Diff::Add("d\n".to_string())
+        ])
+    );
+}
+
+#[test]
+pub fn test_diff_side_by_side() {
+    let dmp = Dmp::default();
+    let diffs = dmp.diff_lines("a\nb\nc\nd\n", "a\nB\ne\nd\nf\n");
+    let view = dmp.diff_side_by_side(&diffs, true);
+    assert_eq!(
+        vec![
+            Row {
+                old: Some((1, "a".to_string())),
+                new: Some((1, "a".to_string())),
+                kind: RowKind::Equal,
+                inline: None
+            },
+            Row {
+                old: Some((2, "b".to_string())),
+                new: Some((2, "B".to_string())),
+                kind: RowKind::Modified,
+                inline: Some(vec![
+                    Diff::Delete("b".to_string()),
+                    Diff::Add("B".to_string())
+                ])
+            },
+            Row {
+                old: Some((3, "c".to_string())),
+                new: Some((3, "e".to_string())),
+                kind: RowKind::Modified,
+                inline: Some(vec![
+                    Diff::Delete("c".to_string()),
+                    Diff::Add("e".to_string())
+                ])
+            },
+            Row {
+                old: Some((4, "d".to_string())),
+                new: Some((4, "d".to_string())),
+                kind: RowKind::Equal,
+                inline: None
+            },
+            Row {
+                old: None,
+                new: Some((5, "f".to_string())),
+                kind: RowKind::Added,
+                inline: None
+            },
+        ],
+        view.rows
+    );
+
+    let diffs = dmp.diff_lines("x\r\ny\r\nz\r\n", "z\r\n");
+    let view = dmp.diff_side_by_side(&diffs, false);
+    assert_eq!(
+        vec![
+            Row {
+                old: Some((1, "x".to_string())),
+                new: None,
+                kind: RowKind::Deleted,
+                inline: None
+            },
+            Row {
+                old: Some((2, "y".to_string())),
+                new: None,
+                kind: RowKind::Deleted,
+                inline: None
+            },
+            Row {
+                old: Some((3, "z".to_string())),
+                new: Some((1, "z".to_string())),
+                kind: RowKind::Equal,
+                inline: None
+            },
+        ],
+        view.rows
+    );
+}
+
+#[test]
+pub fn test_diff_hunks() {
+    let dmp = Dmp::default();
+    let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+    let new = "1\ntwo\n3\n4\n5\n6\n7\n8\n10\n11";
+    let diffs = dmp.diff_lines(old, new);
+
+    let hunks = dmp.diff_hunks(&diffs, 1);
+    assert_eq!(2, hunks.len());
+    assert_eq!("@@ -1,3 +1,3 @@\n 1\n-2\n+two\n 3\n", hunks[0].to_string());
+    assert_eq!(
+        "@@ -8,3 +8,3 @@\n 8\n-9\n 10\n+11\n\\ No newline at end of file\n",
+        hunks[1].to_string()
+    );
+    assert_eq!(
+        vec!["8\n", "9\n", "10\n"],
+        hunks[1].old_lines().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec!["8\n", "10\n", "11"],
+        hunks[1].new_lines().collect::<Vec<_>>()
+    );
+    assert_eq!(4, hunks[1].lines().count());
+
+    // Overlapping context merges the hunks.
+    let hunks = dmp.diff_hunks(&diffs, 3);
+    assert_eq!(1, hunks.len());
+    assert_eq!(
+        (1, 10, 1, 10),
+        (
+            hunks[0].start1,
+            hunks[0].length1,
+            hunks[0].start2,
+            hunks[0].length2
+        )
+    );
+
+    // Pure insertions name the line before them.
+    let diffs = dmp.diff_lines("a\nb\n", "a\nb\nc\n");
+    assert_eq!(
+        "@@ -2,0 +3 @@\n+c\n",
+        dmp.diff_hunks(&diffs, 0)[0].to_string()
+    );
+    assert_eq!(
+        "@@ -0,0 +1 @@\n+x\n",
+        dmp.diff_hunks(&dmp.diff_lines("", "x\n"), 3)[0].to_string()
+    );
+    assert!(dmp.diff_hunks(&dmp.diff_lines(old, old), 3).is_empty());
+}
+
 #[test]
 pub fn test_match_apphabet() {
     let dmp = Dmp::default();
@@ -1724,6 +2232,54 @@ pub fn test_match_main() {
     );
 }
 
+#[test]
+pub fn test_match_main_u16() {
+    let dmp = Dmp::default();
+    // Each emoji is two UTF-16 code units.
+    assert_eq!(4, dmp.match_main_u16("🅰🅱abc🅲", "abc", 0));
+    assert_eq!(4, dmp.match_main_u16("🅰🅱abc🅲", "abc", 4));
+    assert_eq!(2, dmp.match_main("🅰🅱abc🅲", "abc", 2));
+    assert_eq!(-1, dmp.match_main_u16("🅰🅱abc🅲", "xyz", 0));
+    assert_eq!(8, dmp.match_main_unit("éé🅰abc", "abc", 0, LengthUnit::UTF8));
+}
+
+#[test]
+pub fn test_diff_op() {
+    for diff in [
+        Diff::Delete("a".to_string()),
+        Diff::Keep("b".to_string()),
+        Diff::Add("c".to_string()),
+    ] {
+        assert_eq!(
+            Some(diff.clone()),
+            Diff::from_op(diff.op(), diff.text().clone())
+        );
+    }
+    assert_eq!(-1, Diff::Delete("".to_string()).op());
+    assert_eq!(None, Diff::from_op(2, "x".to_string()));
+}
+
+#[test]
+pub fn test_match_main_normalized() {
+    let dmp = Dmp {
+        ignore_case: true,
+        normalization: Normalization::NFC,
+        ..Default::default()
+    };
+    assert_eq!(
+        3,
+        dmp.match_main("Au Cafe\u{301} Noir", "caf\u{e9} noir", 3)
+    );
+    assert_eq!(3, dmp.match_main("Au Cafe\u{301} Noir", "CAF\u{c9}", 0));
+    assert_eq!(-1, dmp.match_main("Au Cafe\u{301} Noir", "the", 0));
+
+    let dmp = Dmp {
+        ignore_whitespace: true,
+        ..Default::default()
+    };
+    assert_eq!(9, dmp.match_main("let  x = y + z;", "y+z", 9));
+}
+
 #[test]
 pub fn test_patch_obj() {
     let mut patch = Patch::new(vec![], 0, 0, 0, 0);
@@ -1756,9 +2312,14 @@ pub fn test_patch_from_text() {
     assert_eq!(strp, dmp.patch_from_text(strp.clone())[0].to_string());
 
     assert_eq!(
-        "@@ -1,1 +1,1 @@\n-a\n+b\n".to_string(),
+        "@@ -1 +1 @@\n-a\n+b\n".to_string(),
         dmp.patch_from_text("@@ -1 +1 @@\n-a\n+b\n".to_string())[0].to_string()
     );
+    // Patch texts of earlier versions, which wrote the length of one.
+    assert_eq!(
+        "@@ -1 +1 @@\n-a\n+b\n".to_string(),
+        dmp.patch_from_text("@@ -1,1 +1,1 @@\n-a\n+b\n".to_string())[0].to_string()
+    );
 
     assert_eq!(
         "@@ -1,3 +0,0 @@\n-abc\n".to_string(),
@@ -1971,53 +2532,168 @@ pub fn test_patch_add_padding() {
 }
 
 #[test]
-pub fn test_patch_apply() {
-    let dmp = Dmp {
-        match_distance: 1000,
-        match_threshold: 0.5,
-        patch_delete_threshold: 0.5,
-        ..Default::default()
-    };
-    // Null case.
-    let mut patches = dmp.patch_make1("", "");
-    let mut results = dmp.patch_apply(&mut patches, "Hello world.");
-    assert_eq!(("Hello world.".chars().collect(), vec![]), results);
+pub fn test_patch_invert() {
+    let dmp = Dmp::default();
+    let text1 = "The quick brown fox jumps over the lazy dog.";
+    let text2 = "That quick brown fox jumped over a lazy dog.";
+    let patches = dmp.patch_make1(text1, text2);
+    let mut inverted = dmp.patch_invert(&patches);
+    assert_eq!(
+        "@@ -1,12 +1,11 @@\n Th\n-at\n+e\n  quick b\n@@ -21,17 +21,18 @@\n jump\n-ed\n+s\n  over \n-a\n+the\n  laz\n",
+        dmp.patch_to_text(&mut inverted)
+    );
+    assert_eq!(
+        (text1.chars().collect(), vec![true, true]),
+        dmp.patch_apply(&mut inverted, text2)
+    );
+    assert_eq!(patches, dmp.patch_invert(&inverted));
 
-    // Exact match.
-    patches = dmp.patch_make1(
-        "The quick brown fox jumps over the lazy dog.",
-        "That quick brown fox jumped over a lazy dog.",
+    // An earlier hunk much larger than Match_MaxBits and the patch margin
+    // moves the later ones out of reach of the fuzzy match.
+    let middle = "0123456789".repeat(40);
+    let text1 = format!("The quick fox. {}The lazy dog.", middle);
+    let text2 = format!("The {}quick fox. {}The lazy cat.", "x".repeat(1500), middle);
+    let patches = dmp.patch_make1(&text1, &text2);
+    assert_eq!(2, patches.len());
+    let inverted = dmp.patch_invert(&patches);
+    assert_eq!(
+        (text1.chars().collect(), vec![true, true]),
+        dmp.patch_apply(&mut inverted.clone(), &text2)
     );
-    results = dmp.patch_apply(&mut patches, "The quick brown fox jumps over the lazy dog.");
     assert_eq!(
         (
-            "That quick brown fox jumped over a lazy dog."
+            format!("The {}quick fox. {}The lazy dog.", "x".repeat(1500), middle)
                 .chars()
                 .collect(),
-            vec![true, true]
+            vec![false, true]
         ),
-        results
-    );
-
-    // Partial match.
-    results = dmp.patch_apply(
-        &mut patches,
-        "The quick red rabbit jumps over the tired tiger.",
+        dmp.patch_apply_selected(&inverted, &[false, true], &text2)
     );
     assert_eq!(
         (
-            "That quick red rabbit jumped over a tired tiger."
+            format!("The quick fox. {}The lazy cat.", middle)
                 .chars()
                 .collect(),
-            vec![true, true]
+            vec![true, false]
         ),
-        results
-    );
-
-    // Failed match.
-    results = dmp.patch_apply(
-        &mut patches,
-        "I am the very model of a modern major general.",
+        dmp.patch_apply_selected(&inverted, &[true, false], &text2)
+    );
+    assert_eq!(patches, dmp.patch_invert(&inverted));
+    assert_eq!(
+        (text2.chars().collect(), vec![true, true]),
+        dmp.patch_apply(&mut dmp.patch_invert(&inverted), &text1)
+    );
+}
+
+#[test]
+pub fn test_patch_apply_selected() {
+    let dmp = Dmp::default();
+    let text1 = "The quick brown fox jumps over the lazy dog.";
+    let text2 = "That quick brown fox jumped over a lazy dog.";
+    let patches = dmp.patch_make1(text1, text2);
+
+    // Only the second hunk.
+    assert_eq!(
+        (
+            "The quick brown fox jumped over a lazy dog."
+                .chars()
+                .collect(),
+            vec![false, true]
+        ),
+        dmp.patch_apply_selected(&patches, &[false, true], text1)
+    );
+    // Only the first one, the mask may be short.
+    assert_eq!(
+        (
+            "That quick brown fox jumps over the lazy dog."
+                .chars()
+                .collect(),
+            vec![true, false]
+        ),
+        dmp.patch_apply_selected(&patches, &[true], text1)
+    );
+    // Revert the first hunk of the new text.
+    assert_eq!(
+        (
+            "The quick brown fox jumped over a lazy dog."
+                .chars()
+                .collect(),
+            vec![true, false]
+        ),
+        dmp.patch_apply_selected(&dmp.patch_invert(&patches), &[true, false], text2)
+    );
+
+    // A selected patch which fails does not shift the ones after it.
+    let block = "lorem ipsum dolor sit amet; ";
+    let patches = dmp.patch_make1(
+        &format!("{}{}{}{}", "0123456789".repeat(6), block, block, block),
+        &format!("{}{}lorem ipsum DOLOR sit amet; ", block, block),
+    );
+    let text = format!("{}{}{}{}", "abcdefghij".repeat(6), block, block, block);
+    assert_eq!(
+        (
+            format!(
+                "{}{}{}lorem ipsum DOLOR sit amet; ",
+                "abcdefghij".repeat(6),
+                block,
+                block
+            )
+            .chars()
+            .collect(),
+            vec![false, true]
+        ),
+        dmp.patch_apply_selected(&patches, &[true, true], &text)
+    );
+}
+
+#[test]
+pub fn test_patch_apply() {
+    let dmp = Dmp {
+        match_distance: 1000,
+        match_threshold: 0.5,
+        patch_delete_threshold: 0.5,
+        ..Default::default()
+    };
+    // Null case.
+    let mut patches = dmp.patch_make1("", "");
+    let mut results = dmp.patch_apply(&mut patches, "Hello world.");
+    assert_eq!(("Hello world.".chars().collect(), vec![]), results);
+
+    // Exact match.
+    patches = dmp.patch_make1(
+        "The quick brown fox jumps over the lazy dog.",
+        "That quick brown fox jumped over a lazy dog.",
+    );
+    results = dmp.patch_apply(&mut patches, "The quick brown fox jumps over the lazy dog.");
+    assert_eq!(
+        (
+            "That quick brown fox jumped over a lazy dog."
+                .chars()
+                .collect(),
+            vec![true, true]
+        ),
+        results
+    );
+
+    // Partial match.
+    results = dmp.patch_apply(
+        &mut patches,
+        "The quick red rabbit jumps over the tired tiger.",
+    );
+    assert_eq!(
+        (
+            "That quick red rabbit jumped over a tired tiger."
+                .chars()
+                .collect(),
+            vec![true, true]
+        ),
+        results
+    );
+
+    // Failed match.
+    results = dmp.patch_apply(
+        &mut patches,
+        "I am the very model of a modern major general.",
     );
     assert_eq!(
         (
@@ -2136,3 +2812,1218 @@ pub fn test_patch_apply() {
     results = dmp.patch_apply(&mut patches, "");
     assert_eq!(("".chars().collect(), vec![true]), results);
 }
+
+#[test]
+pub fn test_patch_set() {
+    let dmp = Dmp::default();
+    let text1 = "The quick brown fox jumps over the lazy dog.";
+    let text2 = "That quick brown fox jumped over a lazy dog.";
+    let mut file = FilePatch::new(
+        Some("a/fox.txt".to_string()),
+        Some("b/fox 100%.txt".to_string()),
+        text1,
+        dmp.patch_make1(text1, text2),
+    );
+    file.new_mode = Some(0o100755);
+    let added = FilePatch::new(
+        None,
+        Some("new.txt".to_string()),
+        "",
+        dmp.patch_make1("", "abc"),
+    );
+    let patch_set = PatchSet {
+        files: vec![file, added],
+    };
+
+    let text = dmp.patch_set_to_text(&patch_set);
+    assert_eq!(
+        "dmp-patch-set 1\nfile\nold-path a/fox.txt\nnew-path b/fox 100%25.txt\nnew-mode 100755\nbase-hash sha1:".to_string(),
+        text[..text.find("sha1:").unwrap() + 5]
+    );
+    assert_eq!(Ok(patch_set.clone()), dmp.patch_set_from_text(&text));
+
+    // Applying checks the base text first.
+    let file = &patch_set.files[0];
+    assert_eq!(
+        Ok((text2.to_string(), vec![true, true])),
+        dmp.patch_set_apply(file, text1)
+    );
+    assert_eq!(
+        Err(PatchSetError::HashMismatch {
+            expected: file.base_hash.clone().unwrap(),
+            actual: text_hash(text2),
+        }),
+        dmp.patch_set_apply(file, text2)
+    );
+    assert_eq!(
+        Ok(("abc".to_string(), vec![true])),
+        dmp.patch_set_apply(&patch_set.files[1], "")
+    );
+    assert_eq!(
+        "sha1:da39a3ee5e6b4b0d3255bfef95601890afd80709",
+        text_hash("")
+    );
+
+    // Malformed sets.
+    assert!(dmp.patch_set_from_text("dmp-patch-set 2\n").is_err());
+    assert!(dmp.patch_set_from_text("file\n").is_err());
+    assert!(dmp
+        .patch_set_from_text("dmp-patch-set 1\nfile\ncolour red\n")
+        .is_err());
+    assert!(dmp
+        .patch_set_from_text("dmp-patch-set 1\nfile\n@@ -1 +1 @@\n*x\n")
+        .is_err());
+    assert_eq!(
+        Ok(PatchSet::default()),
+        dmp.patch_set_from_text("dmp-patch-set 1\n")
+    );
+}
+
+/// Create a fresh directory under the system temp dir, filled with files.
+fn make_tree(name: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+    let root = std::env::temp_dir().join(format!("dmp-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    for (path, text) in files {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, text).unwrap();
+    }
+    root
+}
+
+#[test]
+pub fn test_diff_dirs() {
+    let dmp = Dmp::default();
+    let fox = "The quick brown fox jumps over the lazy dog.\n";
+    let old = make_tree(
+        "dirs-old",
+        &[
+            ("same.txt", "unchanged\n"),
+            ("edit.txt", fox),
+            ("gone.txt", "Removed for good.\n"),
+            ("docs/fox.txt", fox),
+        ],
+    );
+    let new = make_tree(
+        "dirs-new",
+        &[
+            ("same.txt", "unchanged\n"),
+            ("edit.txt", "That quick brown fox jumped over a lazy dog.\n"),
+            (
+                "docs/moved/fox.txt",
+                "The quick brown fox jumps over the lazy cat.\n",
+            ),
+            ("added.txt", "Hello world.\n"),
+        ],
+    );
+
+    let diff = dmp.diff_dirs(&old, &new).unwrap();
+    assert!(diff.skipped.is_empty());
+    let patch_set = diff.patch_set;
+    let paths: Vec<(Option<&str>, Option<&str>)> = patch_set
+        .files
+        .iter()
+        .map(|file| (file.old_path.as_deref(), file.new_path.as_deref()))
+        .collect();
+    assert_eq!(
+        vec![
+            (Some("edit.txt"), Some("edit.txt")),
+            (Some("docs/fox.txt"), Some("docs/moved/fox.txt")),
+            (Some("gone.txt"), None),
+            (None, Some("added.txt")),
+        ],
+        paths
+    );
+
+    // Replay onto a copy of the old tree.
+    let target = make_tree(
+        "dirs-target",
+        &[
+            ("same.txt", "unchanged\n"),
+            ("edit.txt", fox),
+            ("gone.txt", "Removed for good.\n"),
+            ("docs/fox.txt", fox),
+        ],
+    );
+    let results = dmp.apply_dirs(&patch_set, &target);
+    assert!(results.iter().all(|file| file.result.is_ok()));
+    assert_eq!(
+        PatchSet::default(),
+        dmp.diff_dirs(&new, &target).unwrap().patch_set
+    );
+    assert!(!target.join("docs/fox.txt").exists());
+
+    // A tree which differs from the old one is refused file by file.
+    let other = make_tree("dirs-other", &[("edit.txt", "Something else.\n")]);
+    let results = dmp.apply_dirs(&patch_set, &other);
+    assert_eq!("edit.txt", results[0].path);
+    assert!(matches!(
+        results[0].result,
+        Err(ApplyDirError::PatchSet(PatchSetError::HashMismatch { .. }))
+    ));
+    assert!(matches!(results[1].result, Err(ApplyDirError::Io(_))));
+    assert!(results[3].result.is_ok());
+    assert_eq!(
+        "Something else.\n",
+        std::fs::read_to_string(other.join("edit.txt")).unwrap()
+    );
+
+    // Paths may not leave the root, and renames do not overwrite files.
+    let guarded = make_tree("dirs-guarded", &[("a.txt", fox), ("b.txt", "Keep me.\n")]);
+    let mut files: Vec<FilePatch> = ["../escape.txt", "/tmp/escape.txt", "docs//fox.txt", ""]
+        .iter()
+        .map(|path| FilePatch::new(None, Some(path.to_string()), "", dmp.patch_make1("", fox)))
+        .collect();
+    files.push(FilePatch::new(
+        Some("a.txt".to_string()),
+        Some("b.txt".to_string()),
+        fox,
+        dmp.patch_make1(fox, fox),
+    ));
+    let results = dmp.apply_dirs(&PatchSet { files }, &guarded);
+    for file in &results[..4] {
+        assert!(matches!(file.result, Err(ApplyDirError::InvalidPath(_))));
+    }
+    assert!(
+        matches!(&results[4].result, Err(ApplyDirError::Io(err)) if err.kind() == std::io::ErrorKind::AlreadyExists)
+    );
+    assert_eq!(fox, std::fs::read_to_string(guarded.join("a.txt")).unwrap());
+    assert_eq!(
+        "Keep me.\n",
+        std::fs::read_to_string(guarded.join("b.txt")).unwrap()
+    );
+
+    // Files which are not UTF-8 text are reported, not compared.
+    let binary = make_tree("dirs-binary", &[("a.txt", fox), ("b.bin", "")]);
+    std::fs::write(binary.join("b.bin"), [0xff, 0xfe, 0x00]).unwrap();
+    let text = make_tree("dirs-text", &[("a.txt", fox), ("b.bin", "text\n")]);
+    let diff = dmp.diff_dirs(&binary, &text).unwrap();
+    assert_eq!(PatchSet::default(), diff.patch_set);
+    assert_eq!(1, diff.skipped.len());
+    assert_eq!(binary.join("b.bin"), diff.skipped[0].path);
+    assert_eq!(
+        std::io::ErrorKind::InvalidData,
+        diff.skipped[0].error.kind()
+    );
+
+    for root in [old, new, target, other, guarded, binary, text] {
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
+
+#[cfg(unix)]
+#[test]
+pub fn test_apply_dirs_symlink() {
+    let dmp = Dmp::default();
+    let fox = "The quick brown fox jumps over the lazy dog.\n";
+    let outside = make_tree("dirs-outside", &[("a.txt", fox)]);
+    let linked = make_tree("dirs-linked", &[]);
+    std::os::unix::fs::symlink(&outside, linked.join("out")).unwrap();
+    std::os::unix::fs::symlink(outside.join("a.txt"), linked.join("a.txt")).unwrap();
+
+    // Neither a linked directory nor a linked file is followed.
+    let files = vec![
+        FilePatch::new(
+            None,
+            Some("out/new.txt".to_string()),
+            "",
+            dmp.patch_make1("", fox),
+        ),
+        FilePatch::new(
+            Some("a.txt".to_string()),
+            Some("a.txt".to_string()),
+            fox,
+            dmp.patch_make1(fox, "Changed.\n"),
+        ),
+    ];
+    let results = dmp.apply_dirs(&PatchSet { files }, &linked);
+    for file in &results {
+        assert!(matches!(file.result, Err(ApplyDirError::InvalidPath(_))));
+    }
+    assert!(!outside.join("new.txt").exists());
+    assert_eq!(fox, std::fs::read_to_string(outside.join("a.txt")).unwrap());
+
+    for root in [outside, linked] {
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
+
+#[test]
+pub fn test_similarity() {
+    let dmp = Dmp::default();
+    assert_eq!(1.0, dmp.similarity("", "", false));
+    assert_eq!(1.0, dmp.similarity("abc", "abc", false));
+    assert_eq!(0.0, dmp.similarity("abc", "xyz", false));
+    assert_eq!(0.75, dmp.similarity("abcd", "abxd", false));
+
+    // By lines a changed line counts in full.
+    let text1 = "alpha\nbeta\ngamma\ndelta\n";
+    let text2 = "alpha\nbeta\ngamma\nDelta\n";
+    assert_eq!(1.0 - 1.0 / 23.0, dmp.similarity(text1, text2, false));
+    assert_eq!(1.0 - 6.0 / 23.0, dmp.similarity(text1, text2, true));
+
+    // Pairing takes the best matches first.
+    let deleted = ["The quick brown fox.", "Hello world.", "Nothing alike"];
+    let added = [
+        "Hello world!",
+        "The quick brown fox!",
+        "The quick brown fox.",
+    ];
+    assert_eq!(
+        vec![
+            SimilarPair {
+                deleted: 0,
+                added: 2,
+                score: 1.0
+            },
+            SimilarPair {
+                deleted: 1,
+                added: 0,
+                score: 1.0 - 1.0 / 12.0
+            },
+        ],
+        dmp.similarity_pairs(&deleted, &added, 0.5, false)
+    );
+    assert_eq!(1, dmp.similarity_pairs(&deleted, &added, 1.0, false).len());
+}
+
+#[test]
+pub fn test_diff_stats() {
+    let dmp = Dmp::default();
+    let diffs = vec![
+        Diff::Keep("Größe: ".to_string()),
+        Diff::Delete("zwölf".to_string()),
+        Diff::Add("elf".to_string()),
+        Diff::Keep(" Äpfel\nund ".to_string()),
+        Diff::Add("drei Birnen".to_string()),
+        Diff::Keep("\nEnde\n".to_string()),
+    ];
+    assert_eq!(
+        DiffStats {
+            inserted_chars: 14,
+            deleted_chars: 5,
+            unchanged_chars: 24,
+            inserted_words: 3,
+            deleted_words: 1,
+            unchanged_words: 4,
+            inserted_lines: 2,
+            deleted_lines: 2,
+            unchanged_lines: 1,
+            edit_regions: 2,
+            levenshtein: 16,
+        },
+        dmp.diff_stats(&diffs)
+    );
+    assert_eq!(DiffStats::default(), dmp.diff_stats(&[]));
+
+    let small = DiffStats {
+        inserted_lines: 3,
+        deleted_lines: 2,
+        ..Default::default()
+    };
+    let big = DiffStats {
+        inserted_lines: 100,
+        deleted_lines: 0,
+        ..Default::default()
+    };
+    assert_eq!(
+        " a.txt | 5 +++--\n 1 file changed, 3 insertions(+), 2 deletions(-)\n",
+        dmp.diff_stats_to_text(&[("a.txt", small)], 80)
+    );
+    assert_eq!(
+        " a.txt     |   5 +-\n src/b.txt | 100 +++++++++++\n 2 files changed, 103 insertions(+), 2 deletions(-)\n",
+        dmp.diff_stats_to_text(&[("a.txt", small), ("src/b.txt", big)], 28)
+    );
+    assert_eq!(
+        " 0 files changed, 0 insertions(+), 0 deletions(-)\n",
+        dmp.diff_stats_to_text(&[], 80)
+    );
+}
+
+#[test]
+pub fn test_patch_length_unit() {
+    let dmp = Dmp::default();
+    let text1 = "🅰🅰🅰🅰🅰🅰 x";
+    let text2 = "🅰🅰🅰🅰🅰🅰 y";
+    for (unit, header) in [
+        (LengthUnit::UnicodeScalar, "@@ -4,5 +4,5 @@"),
+        (LengthUnit::UTF16, "@@ -7,8 +7,8 @@"),
+        (LengthUnit::UTF8, "@@ -13,14 +13,14 @@"),
+    ] {
+        let mut diffs = dmp.diff_main(text1, text2, false);
+        let mut patches = dmp.patch_make_unit(text1, &mut diffs, unit);
+        let text = dmp.patch_to_text(&mut patches);
+        assert_eq!(
+            format!("{header}\n %F0%9F%85%B0%F0%9F%85%B0%F0%9F%85%B0 \n-x\n+y\n"),
+            text
+        );
+        assert_eq!(patches, dmp.patch_from_text_unit(text, unit));
+        assert_eq!(
+            (text2.chars().collect(), vec![true]),
+            dmp.patch_apply_unit(&mut patches, text1, unit)
+        );
+        // Found again after the text in front of it changed.
+        assert_eq!(
+            ("ж🅰🅰🅰🅰🅰🅰 y".chars().collect(), vec![true]),
+            dmp.patch_apply_unit(&mut patches, "ж🅰🅰🅰🅰🅰🅰 x", unit)
+        );
+    }
+
+    // Long patches are split on char boundaries.
+    let text1 = "😀a".repeat(30);
+    let text2 = "😀b".repeat(30);
+    for unit in [
+        LengthUnit::UnicodeScalar,
+        LengthUnit::UTF16,
+        LengthUnit::UTF8,
+    ] {
+        let mut diffs = dmp.diff_main(&text1, &text2, false);
+        let mut patches = dmp.patch_make_unit(&text1, &mut diffs, unit);
+        assert_eq!(1, patches.len());
+        dmp.patch_splitmax_unit(&mut patches, unit);
+        assert!(patches.len() > 1);
+        let (patched, results) = dmp.patch_apply_unit(&mut patches, &text1, unit);
+        assert_eq!(text2, patched.iter().collect::<String>());
+        assert!(results.iter().all(|applied| *applied));
+    }
+}
+
+proptest! {
+    #[test]
+    fn prop_patch_apply_roundtrip(
+        text1 in "[ab \n.àéΩжл中文😀🅰\u{301}]{0,60}",
+        text2 in "[ab \n.àéΩжл中文😀🅰\u{301}]{0,60}",
+    ) {
+        let dmp = Dmp::default();
+        for unit in [LengthUnit::UnicodeScalar, LengthUnit::UTF16, LengthUnit::UTF8] {
+            let mut diffs = dmp.diff_main(&text1, &text2, false);
+            let mut patches = dmp.patch_make_unit(&text1, &mut diffs, unit);
+            let text = dmp.patch_to_text(&mut patches);
+            let mut parsed = dmp.patch_from_text_unit(text, unit);
+            prop_assert_eq!(&patches, &parsed);
+            let (patched, _) = dmp.patch_apply_unit(&mut parsed, &text1, unit);
+            prop_assert_eq!(&text2, &patched.iter().collect::<String>());
+        }
+    }
+
+    #[test]
+    fn prop_patch_make_roundtrip(
+        text1 in "[ab \n.àéΩжл中文😀🅰\u{301}]{0,60}",
+        text2 in "[ab \n.àéΩжл中文😀🅰\u{301}]{0,60}",
+    ) {
+        let dmp = Dmp::default();
+        let mut patches = dmp.patch_make1(&text1, &text2);
+        let (patched, _) = dmp.patch_apply(&mut patches, &text1);
+        prop_assert_eq!(&text2, &patched.iter().collect::<String>());
+    }
+
+    #[test]
+    fn prop_diff_reconstructs_texts(
+        text1 in "[ab \n.àéΩжл中文😀🅰\u{301}]{0,60}",
+        text2 in "[ab \n.àéΩжл中文😀🅰\u{301}]{0,60}",
+        checklines: bool,
+    ) {
+        let dmp = Dmp::default();
+        let mut diffs = dmp.diff_main(&text1, &text2, checklines);
+        prop_assert_eq!(&text1, &dmp.diff_text1(&mut diffs));
+        prop_assert_eq!(&text2, &dmp.diff_text2(&mut diffs));
+    }
+
+    #[test]
+    fn prop_delta_roundtrip(
+        text1 in "[ab \n.àéΩжл中文😀🅰\u{301}%\t]{0,60}",
+        text2 in "[ab \n.àéΩжл中文😀🅰\u{301}%\t]{0,60}",
+    ) {
+        let dmp = Dmp::default();
+        let mut diffs = dmp.diff_main(&text1, &text2, false);
+        for unit in [LengthUnit::UnicodeScalar, LengthUnit::UTF16, LengthUnit::UTF8] {
+            let delta = dmp.diff_todelta_unit(&mut diffs, unit);
+            prop_assert_eq!(&diffs, &dmp.diff_from_delta_unit(&text1, &delta, unit));
+        }
+        let delta = dmp.diff_todelta_unit(&mut diffs, LengthUnit::UTF16);
+        prop_assert_eq!(&text2, &dmp.diff_text2_from_delta_u16(&text1, &delta));
+    }
+
+    #[test]
+    fn prop_cleanup_preserves_texts(
+        text1 in "[ab \n.àéΩжл中文😀🅰\u{301}]{0,60}",
+        text2 in "[ab \n.àéΩжл中文😀🅰\u{301}]{0,60}",
+    ) {
+        let dmp = Dmp {
+            edit_cost: 4,
+            ..Dmp::default()
+        };
+        let diffs = dmp.diff_main(&text1, &text2, false);
+        let cleanups: [fn(&Dmp, &mut Vec<Diff>); 4] = [
+            Dmp::diff_cleanup_merge,
+            Dmp::diff_cleanup_semantic,
+            Dmp::diff_cleanup_semantic_lossless,
+            Dmp::diff_cleanup_efficiency,
+        ];
+        for cleanup in cleanups {
+            let mut cleaned = diffs.clone();
+            cleanup(&dmp, &mut cleaned);
+            prop_assert_eq!(&text1, &dmp.diff_text1(&mut cleaned));
+            prop_assert_eq!(&text2, &dmp.diff_text2(&mut cleaned));
+        }
+    }
+
+    #[test]
+    fn prop_parsers_never_panic(
+        text in "[ab😀]{0,6}",
+        input in "(@@ [-+0-9, ]{0,10}@@\n([-+ =][a-z😀%0-9A-F]{0,4}\n){0,3}|[-+=][0-9a-z😀%]{0,3}\t|file\n|[a-z-]{0,8} [^\n]{0,6}\n){0,5}",
+    ) {
+        let dmp = Dmp::default();
+        for unit in [LengthUnit::UnicodeScalar, LengthUnit::UTF16, LengthUnit::UTF8] {
+            let _ = dmp.patch_parse_unit(&input, unit);
+            let _ = dmp.diff_parse_delta(&text, &input, unit);
+        }
+        let _ = dmp.patch1_parse(&input);
+        let _ = dmp.patch_set_from_text(&format!("dmp-patch-set 1\n{input}"));
+    }
+}
+
+#[test]
+pub fn test_parse_errors() {
+    let dmp = Dmp::default();
+    assert!(dmp.patch1_parse("").is_err());
+    assert!(dmp.patch1_parse("-1 +1 @@\n*a\n").is_err());
+    assert!(dmp.patch1_parse("-1,2,3,4,5 @@\n").is_err());
+    assert!(dmp.patch_parse("@@ -1 +1 @@\n-%E0\n").is_err());
+
+    for unit in [
+        LengthUnit::UnicodeScalar,
+        LengthUnit::UTF16,
+        LengthUnit::UTF8,
+    ] {
+        // Unknown operation, bad number, too long, too short.
+        assert!(dmp.diff_parse_delta("abc", "é3", unit).is_err());
+        assert!(dmp.diff_parse_delta("abc", "=x", unit).is_err());
+        assert!(dmp.diff_parse_delta("abc", "=-3", unit).is_err());
+        assert!(dmp.diff_parse_delta("abc", "=4", unit).is_err());
+        assert!(dmp.diff_parse_delta("abc", "=2", unit).is_err());
+        assert!(dmp
+            .diff_parse_delta("abc", &format!("={}", usize::MAX), unit)
+            .is_err());
+    }
+    // Half a char.
+    assert!(dmp
+        .diff_parse_delta("é", "=1\t=1", LengthUnit::UTF8)
+        .is_err());
+    // Deltas of the UTF-16 ports may split a surrogate pair.
+    assert_eq!(
+        Ok(vec![Diff::Keep("🅰".to_string())]),
+        dmp.diff_parse_delta("🅰", "=1\t=1", LengthUnit::UTF16)
+    );
+    assert_eq!(
+        Ok(vec![
+            Diff::Delete("🅰".to_string()),
+            Diff::Add("🅱".to_string())
+        ]),
+        dmp.diff_parse_delta("🅰", "=1\t-1\t+%ED%B5%B1", LengthUnit::UTF16)
+    );
+}
+
+#[test]
+pub fn test_patch_u16() {
+    let dmp = Dmp::default();
+    // Offsets of the JavaScript port count the surrogate pairs of the emoji twice.
+    let text1 = "🅰🅱 The quick brown fox";
+    let text2 = "🅰🅱 The quick red fox";
+    let text = "@@ -12,13 +12,11 @@\n ick \n-brown\n+red\n  fox\n";
+    let mut patches = dmp.patch_make1_u16(text1, text2);
+    assert_eq!(text, dmp.patch_to_text_u16(&mut patches));
+    assert_eq!(
+        "@@ -10,13 +10,11 @@\n ick \n-brown\n+red\n  fox\n",
+        dmp.patch_to_text(&mut dmp.patch_make1(text1, text2))
+    );
+    let mut diffs = dmp.diff_main(text1, text2, false);
+    dmp.diff_cleanup_semantic(&mut diffs);
+    assert_eq!(patches, dmp.patch_make2_u16(&mut diffs));
+    assert_eq!(patches, dmp.patch_make3_u16(text1, text2, &mut diffs));
+    assert_eq!(patches, dmp.patch_make4_u16(text1, &mut diffs));
+
+    let mut patches = dmp.patch_from_text_u16(text.to_string());
+    assert_eq!(
+        (text2.chars().collect(), vec![true]),
+        dmp.patch_apply_u16(&mut patches, text1)
+    );
+    // Many more emoji in front still leave the patch within reach.
+    assert_eq!(
+        (("😀".repeat(20) + text2).chars().collect(), vec![true]),
+        dmp.patch_apply_u16(&mut patches, &("😀".repeat(20) + text1))
+    );
+}
+
+#[test]
+pub fn test_dmp_builder() {
+    let dmp = Dmp::builder()
+        .diff_timeout(Some(Duration::from_millis(500)))
+        .match_threshold(0.25)
+        .match_maxbits(None)
+        .patch_margin(100)
+        .build()
+        .unwrap();
+    assert_eq!(Some(Duration::from_millis(500)), dmp.diff_timeout);
+    assert_eq!(0.25, dmp.match_threshold);
+    assert_eq!(0, dmp.match_maxbits);
+    // Unlimited bits leave room for any margin.
+    let mut patches = dmp.patch_make1("abcabcabc", "abcabXcabc");
+    assert_eq!(
+        ("abcabXcabc".chars().collect(), vec![true]),
+        dmp.patch_apply(&mut patches, "abcabcabc")
+    );
+
+    assert_eq!(
+        Err(ConfigError::InvalidThreshold {
+            name: "match_threshold",
+            value: -0.5
+        }),
+        Dmp::builder().match_threshold(-0.5).build().map(|_| ())
+    );
+    assert!(matches!(
+        Dmp::builder().patch_delete_threshold(f32::NAN).build(),
+        Err(ConfigError::InvalidThreshold {
+            name: "patch_delete_threshold",
+            ..
+        })
+    ));
+    assert!(Dmp::builder().rename_threshold(1.5).build().is_err());
+    assert_eq!(
+        Err(ConfigError::PatchMarginTooLarge {
+            patch_margin: 16,
+            match_maxbits: 32
+        }),
+        Dmp::builder().patch_margin(16).build().map(|_| ())
+    );
+    assert!(Dmp::builder().patch_margin(15).build().is_ok());
+
+    // Presets are valid and can be adjusted.
+    for preset in [Dmp::fast(), Dmp::precise(), Dmp::code()] {
+        assert_eq!(Ok(()), preset.validate());
+    }
+    assert_eq!(None, Dmp::precise().diff_timeout);
+    let dmp = DmpBuilder::from(Dmp::code())
+        .ignore_case(true)
+        .build()
+        .unwrap();
+    assert_eq!(8, dmp.patch_margin);
+    assert!(dmp.ignore_case);
+
+    // Patches of the code preset give back the new text exactly.
+    let dmp = Dmp::code();
+    let text1 = "fn a() {\n    x\n}\n";
+    let text2 = "fn a() {   \n    y   x\n}\n";
+    let mut patches = dmp.patch_make1(text1, text2);
+    assert_eq!(
+        (text2.chars().collect(), vec![true]),
+        dmp.patch_apply(&mut patches, text1)
+    );
+}
+
+/// Sync every client until nothing changes any more.
+fn sync_until_stable(clients: &mut [SyncClient], transport: &mut InMemoryTransport) {
+    let dmp = Dmp::default();
+    for _ in 0..10 {
+        let before: Vec<String> = clients.iter().map(|client| client.text.clone()).collect();
+        for client in clients.iter_mut() {
+            client.sync(&dmp, transport).unwrap();
+        }
+        if clients
+            .iter()
+            .zip(&before)
+            .all(|(client, text)| &client.text == text && client.text == transport.server.text)
+        {
+            return;
+        }
+    }
+    panic!("clients did not converge");
+}
+
+#[test]
+pub fn test_sync_converges() {
+    let dmp = Dmp::default();
+    let mut transport = InMemoryTransport::new(
+        SyncServer::new("The quick brown fox jumps over the lazy dog."),
+        Dmp::default(),
+    );
+    let mut clients: Vec<SyncClient> = (0..3).map(|id| SyncClient::new(id, "")).collect();
+    sync_until_stable(&mut clients, &mut transport);
+    assert_eq!(
+        "The quick brown fox jumps over the lazy dog.",
+        clients[2].text
+    );
+
+    // Concurrent edits to different parts of the text all survive.
+    clients[0].text = clients[0].text.replace("quick", "slow");
+    clients[1].text = clients[1].text.replace("fox", "cat");
+    clients[2].text = clients[2].text.replace("lazy", "sleepy");
+    sync_until_stable(&mut clients, &mut transport);
+    assert_eq!(
+        "The slow brown cat jumps over the sleepy dog.",
+        transport.server.text
+    );
+
+    // A client joining with text of its own merges it in.
+    let mut late = SyncClient::new(9, "Title\n");
+    late.sync(&dmp, &mut transport).unwrap();
+    late.text.push_str("Footer\n");
+    clients[0].text.insert_str(0, "> ");
+    sync_until_stable(&mut clients, &mut transport);
+    late.sync(&dmp, &mut transport).unwrap();
+    sync_until_stable(&mut clients, &mut transport);
+    assert_eq!(
+        "Title\n> The slow brown cat jumps over the sleepy dog.Footer\n",
+        late.text
+    );
+    assert_eq!(transport.server.text, late.text);
+}
+
+#[test]
+pub fn test_sync_lost_messages() {
+    let mut transport = InMemoryTransport::new(SyncServer::new("abc def ghi"), Dmp::default());
+    let mut clients: Vec<SyncClient> = (0..2).map(|id| SyncClient::new(id, "")).collect();
+    sync_until_stable(&mut clients, &mut transport);
+
+    // A lost message is sent again with the next one.
+    let dmp = Dmp::default();
+    clients[0].text = "abc DEF ghi".to_string();
+    transport.lose_messages(1);
+    clients[0].sync(&dmp, &mut transport).unwrap();
+    assert_eq!("abc def ghi", transport.server.text);
+    clients[0].text.push_str(" jkl");
+    sync_until_stable(&mut clients, &mut transport);
+    assert_eq!("abc DEF ghi jkl", transport.server.text);
+
+    // A lost reply is rebuilt from the backup shadow, without applying
+    // anything twice.
+    clients[1].text = "ABC DEF ghi jkl".to_string();
+    clients[1].sync(&dmp, &mut transport).unwrap();
+    clients[0].text = "abc DEF ghi jkl mno".to_string();
+    transport.lose_replies(1);
+    clients[0].sync(&dmp, &mut transport).unwrap();
+    clients[0].text.push_str(" pqr");
+    sync_until_stable(&mut clients, &mut transport);
+    assert_eq!("ABC DEF ghi jkl mno pqr", transport.server.text);
+
+    // The same when the message whose reply is lost brought no edits.
+    let mut transport = InMemoryTransport::new(SyncServer::new("abc"), Dmp::default());
+    let mut client = SyncClient::new(0, "");
+    client.sync(&dmp, &mut transport).unwrap();
+    transport.server.text = "abc def".to_string();
+    transport.lose_replies(1);
+    client.sync(&dmp, &mut transport).unwrap();
+    assert_eq!("abc", client.text);
+    client.text = "xyz abc".to_string();
+    client.sync(&dmp, &mut transport).unwrap();
+    client.sync(&dmp, &mut transport).unwrap();
+    assert_eq!("xyz abc def", transport.server.text);
+    assert_eq!("xyz abc def", client.text);
+
+    // A delta which does not fit the shadow is rejected.
+    let mut server = SyncServer::new("");
+    let message = SyncMessage {
+        ack: 0,
+        edits: vec![SyncEdit {
+            version: 0,
+            delta: "=5".to_string(),
+        }],
+    };
+    assert!(matches!(
+        server.handle(&dmp, 0, &message),
+        Err(SyncError::Delta(_))
+    ));
+    let message = SyncMessage {
+        ack: 3,
+        edits: vec![],
+    };
+    assert_eq!(
+        Err(SyncError::VersionMismatch {
+            expected: 0,
+            received: 3
+        }),
+        server.handle(&dmp, 1, &message)
+    );
+}
+
+fn history_texts() -> Vec<String> {
+    let mut text = "The quick brown fox jumps over the lazy dog.\n".repeat(4);
+    let mut texts = vec![text.clone()];
+    for (i, word) in ["red", "cat", "hops", "under", "a", "sleepy", "bird", "mat"]
+        .iter()
+        .enumerate()
+    {
+        let line = i % 4 * 45;
+        text.replace_range(line + 4..line + 9, word);
+        text.insert_str(line, &format!("{i}. "));
+        texts.push(text.clone());
+    }
+    texts.push("".to_string());
+    texts.push("Start over.\n".to_string());
+    texts
+}
+
+#[test]
+pub fn test_revision_store() {
+    let texts = history_texts();
+    let mut store = RevisionStore::new(MemoryStorage::new(), 4);
+    for (i, text) in texts.iter().enumerate() {
+        assert_eq!(i as u64, store.commit(text).unwrap());
+    }
+    assert_eq!(texts.len() as u64, store.revisions().unwrap());
+    for (i, text) in texts.iter().enumerate() {
+        assert_eq!(*text, store.checkout(i as u64).unwrap());
+    }
+    // Snapshots at most every 4 revisions, deltas in between.
+    let records: Vec<Revision> = (0..texts.len() as u64)
+        .map(|i| store.storage().load(i).unwrap())
+        .collect();
+    assert!(matches!(records[0], Revision::Snapshot(_)));
+    assert!(matches!(records[1], Revision::Delta(_)));
+    assert!(matches!(records[4], Revision::Snapshot(_)));
+    assert!(matches!(records[5], Revision::Delta(_)));
+
+    let dmp = Dmp::default();
+    for (revision1, revision2) in [(0, 8), (2, 7), (5, 6), (6, 5), (8, 1), (3, 3)] {
+        let mut diffs = store.diff(revision1, revision2).unwrap();
+        assert_eq!(texts[revision1 as usize], dmp.diff_text1(&mut diffs));
+        assert_eq!(texts[revision2 as usize], dmp.diff_text2(&mut diffs));
+    }
+    assert!(matches!(
+        store.checkout(99),
+        Err(HistoryError::NotFound(99))
+    ));
+    assert!(matches!(store.diff(1, 99), Err(HistoryError::NotFound(99))));
+
+    // Compaction follows a new interval without changing any revision.
+    assert!(store.compact(2).unwrap() > 0);
+    assert_eq!(0, store.compact(2).unwrap());
+    assert!(matches!(
+        store.storage().load(2).unwrap(),
+        Revision::Snapshot(_)
+    ));
+    for (i, text) in texts.iter().enumerate() {
+        assert_eq!(*text, store.checkout(i as u64).unwrap());
+    }
+    assert!(store.compact(1).unwrap() > 0);
+    for i in 0..texts.len() as u64 {
+        assert!(matches!(
+            store.storage().load(i).unwrap(),
+            Revision::Snapshot(_)
+        ));
+    }
+}
+
+#[test]
+pub fn test_revision_store_fs() {
+    let texts = history_texts();
+    let dir = make_tree("history", &[]);
+    let mut store = RevisionStore::new(FsStorage::open(&dir).unwrap(), 3);
+    for text in &texts[..5] {
+        store.commit(text).unwrap();
+    }
+    // A new store picks up where the old one left off.
+    let mut store = RevisionStore::new(FsStorage::open(&dir).unwrap(), 3);
+    for text in &texts[5..] {
+        store.commit(text).unwrap();
+    }
+    assert_eq!(texts.len() as u64, store.revisions().unwrap());
+    for (i, text) in texts.iter().enumerate() {
+        assert_eq!(*text, store.checkout(i as u64).unwrap());
+    }
+    assert_eq!(
+        format!("snapshot\n{}", texts[0]),
+        std::fs::read_to_string(dir.join("0.rev")).unwrap()
+    );
+    assert!(std::fs::read_to_string(dir.join("1.rev"))
+        .unwrap()
+        .starts_with("delta\n"));
+
+    std::fs::write(dir.join("1.rev"), "delta\n=9999").unwrap();
+    assert!(matches!(store.checkout(2), Err(HistoryError::Parse(_))));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+pub fn test_blame() {
+    let revisions = [
+        ("alice", "a\nb\nc\n"),
+        ("bob", "a\nB\nc\nd\n"),
+        ("carol", "a\nB\n  c\nd\ne"),
+    ];
+    let blame = |dmp: &Dmp| -> Vec<(String, usize, String)> {
+        dmp.blame(&revisions)
+            .into_iter()
+            .map(|line| (line.line, line.revision, line.author))
+            .collect()
+    };
+    let owned = |lines: &[(&str, usize, &str)]| -> Vec<(String, usize, String)> {
+        lines
+            .iter()
+            .map(|(line, revision, author)| (line.to_string(), *revision, author.to_string()))
+            .collect()
+    };
+    assert_eq!(
+        owned(&[
+            ("a\n", 0, "alice"),
+            ("B\n", 1, "bob"),
+            ("  c\n", 2, "carol"),
+            ("d\n", 1, "bob"),
+            ("e", 2, "carol"),
+        ]),
+        blame(&Dmp::default())
+    );
+
+    // A whitespace change keeps the origin of the line.
+    let dmp = Dmp {
+        ignore_space: IgnoreSpace::All,
+        ..Dmp::default()
+    };
+    assert_eq!(
+        owned(&[
+            ("a\n", 0, "alice"),
+            ("B\n", 1, "bob"),
+            ("  c\n", 0, "alice"),
+            ("d\n", 1, "bob"),
+            ("e", 2, "carol"),
+        ]),
+        blame(&dmp)
+    );
+
+    // Lines removed and added back count as new.
+    let revisions = [("alice", "x\ny\n"), ("bob", "y\n"), ("carol", "x\ny\n")];
+    assert_eq!(
+        vec![(2, "carol"), (0, "alice")],
+        Dmp::default()
+            .blame(&revisions)
+            .iter()
+            .map(|line| (line.revision, line.author.as_str()))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(Vec::<BlameLine>::new(), Dmp::default().blame(&[]));
+    assert_eq!(1, Dmp::default().blame(&[("alice", "only")]).len());
+}
+
+#[test]
+pub fn test_diff_cleanup_code_scoring() {
+    let lossless = |dmp: &Dmp, text1: &str, text2: &str| -> Vec<Diff> {
+        let mut diffs = dmp.diff_main(text1, text2, false);
+        dmp.diff_cleanup_semantic_lossless(&mut diffs);
+        diffs
+    };
+    let prose = Dmp::default();
+    let code = Dmp {
+        boundary_scoring: BoundaryScoring::Code,
+        ..Dmp::default()
+    };
+    assert_eq!(BoundaryScoring::Code, Dmp::code().boundary_scoring);
+
+    // Inside of brackets.
+    assert_eq!(
+        vec![
+            Diff::Keep("call(a,".to_string()),
+            Diff::Add(" b,".to_string()),
+            Diff::Keep(" b)".to_string())
+        ],
+        lossless(&prose, "call(a, b)", "call(a, b, b)")
+    );
+    assert_eq!(
+        vec![
+            Diff::Keep("call(a, b".to_string()),
+            Diff::Add(", b".to_string()),
+            Diff::Keep(")".to_string())
+        ],
+        lossless(&code, "call(a, b)", "call(a, b, b)")
+    );
+
+    // camelCase.
+    assert_eq!(
+        vec![
+            Diff::Keep("fooBar".to_string()),
+            Diff::Add("Bar".to_string()),
+            Diff::Keep("Baz".to_string())
+        ],
+        lossless(&code, "fooBarBaz", "fooBarBarBaz")
+    );
+
+    // Whole blocks, by indentation.
+    assert_eq!(
+        vec![
+            Diff::Keep("{\n  a;\n}\n{\n".to_string()),
+            Diff::Add("  c;\n}\n{\n".to_string()),
+            Diff::Keep("  b;\n}\n".to_string())
+        ],
+        lossless(
+            &prose,
+            "{\n  a;\n}\n{\n  b;\n}\n",
+            "{\n  a;\n}\n{\n  c;\n}\n{\n  b;\n}\n"
+        )
+    );
+    assert_eq!(
+        vec![
+            Diff::Keep("{\n  a;\n}\n".to_string()),
+            Diff::Add("{\n  c;\n}\n".to_string()),
+            Diff::Keep("{\n  b;\n}\n".to_string())
+        ],
+        lossless(
+            &code,
+            "{\n  a;\n}\n{\n  b;\n}\n",
+            "{\n  a;\n}\n{\n  c;\n}\n{\n  b;\n}\n"
+        )
+    );
+    // Blank lines separate blocks.
+    assert_eq!(
+        vec![
+            Diff::Keep("fn a() {\n    x();\n}\n\n".to_string()),
+            Diff::Add("fn c() {\n    z();\n}\n\n".to_string()),
+            Diff::Keep("fn b() {\n    y();\n}\n".to_string())
+        ],
+        lossless(
+            &code,
+            "fn a() {\n    x();\n}\n\nfn b() {\n    y();\n}\n",
+            "fn a() {\n    x();\n}\n\nfn c() {\n    z();\n}\n\nfn b() {\n    y();\n}\n"
+        )
+    );
+}
+
+// Slides edits onto HTML tags, between two tags best.
+struct TagScorer;
+
+impl BoundaryScorer for TagScorer {
+    fn score(&self, one: &[char], two: &[char]) -> i32 {
+        let after_tag = one.last().is_none_or(|ch| *ch == '>');
+        let before_tag = two.first().is_none_or(|ch| *ch == '<');
+        match (after_tag, before_tag) {
+            (true, true) => 20,
+            (true, false) | (false, true) => 10,
+            _ => BoundaryScoring::Prose.score(one, two),
+        }
+    }
+}
+
+#[test]
+pub fn test_diff_cleanup_custom_scoring() {
+    let lossless = |dmp: &Dmp, text1: &str, text2: &str| -> Vec<Diff> {
+        let mut diffs = dmp.diff_main(text1, text2, false);
+        dmp.diff_cleanup_semantic_lossless(&mut diffs);
+        diffs
+    };
+    let prose = Dmp::default();
+    let tags = DmpBuilder::new()
+        .boundary_scorer(TagScorer)
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        vec![
+            Diff::Keep("<li>a</li><li>".to_string()),
+            Diff::Add("c</li><li>".to_string()),
+            Diff::Keep("b</li>".to_string())
+        ],
+        lossless(
+            &prose,
+            "<li>a</li><li>b</li>",
+            "<li>a</li><li>c</li><li>b</li>"
+        )
+    );
+    assert_eq!(
+        vec![
+            Diff::Keep("<li>a</li>".to_string()),
+            Diff::Add("<li>c</li>".to_string()),
+            Diff::Keep("<li>b</li>".to_string())
+        ],
+        lossless(
+            &tags,
+            "<li>a</li><li>b</li>",
+            "<li>a</li><li>c</li><li>b</li>"
+        )
+    );
+    assert_eq!(
+        vec![
+            Diff::Keep("<b>x</b> ".to_string()),
+            Diff::Add("<u>z</u> ".to_string()),
+            Diff::Keep("<i>y</i>".to_string())
+        ],
+        lossless(&tags, "<b>x</b> <i>y</i>", "<b>x</b> <u>z</u> <i>y</i>")
+    );
+
+    // Semantic cleanup goes through the same scorer.
+    let mut diffs = tags.diff_main(
+        "<li>a</li><li>b</li>",
+        "<li>a</li><li>c</li><li>b</li>",
+        false,
+    );
+    tags.diff_cleanup_semantic(&mut diffs);
+    assert_eq!(Diff::Add("<li>c</li>".to_string()), diffs[1]);
+
+    assert_eq!(tags.boundary_scoring, tags.clone().boundary_scoring);
+    assert_ne!(tags.boundary_scoring, BoundaryScoring::custom(TagScorer));
+}
+
+#[test]
+pub fn test_diff_cleanup_unicode_scoring() {
+    let dmp = Dmp {
+        boundary_scoring: BoundaryScoring::Unicode,
+        ..Dmp::default()
+    };
+    let chars = |text: &str| -> Vec<char> { text.chars().collect() };
+    let score = |one: &str, two: &str| BoundaryScoring::Unicode.score(&chars(one), &chars(two));
+    assert_eq!(6, score("", "a"));
+    assert_eq!(4, score("one\n", "two"));
+    assert_eq!(3, score("晴れ。", "明日"));
+    assert_eq!(2, score("one ", "two"));
+    assert_eq!(1, score("明日", "は"));
+    assert_eq!(0, score("明", "日"));
+    // Never inside a grapheme cluster.
+    assert!(score("cafe", "\u{301}") < 0);
+
+    // Prose results for spaced text.
+    let mut diffs = vec![
+        Diff::Keep("The c".to_string()),
+        Diff::Add("at c".to_string()),
+        Diff::Keep("ame.".to_string()),
+    ];
+    dmp.diff_cleanup_semantic_lossless(&mut diffs);
+    assert_eq!(
+        vec![
+            Diff::Keep("The ".to_string()),
+            Diff::Add("cat ".to_string()),
+            Diff::Keep("came.".to_string())
+        ],
+        diffs
+    );
+
+    // Japanese words are not split.
+    let semantic = |dmp: &Dmp, text1: &str, text2: &str| -> Vec<Diff> {
+        let mut diffs = dmp.diff_main(text1, text2, false);
+        dmp.diff_cleanup_semantic(&mut diffs);
+        diffs
+    };
+    assert_eq!(
+        vec![
+            Diff::Keep("明日会".to_string()),
+            Diff::Add("社で会".to_string()),
+            Diff::Keep("議があります。".to_string())
+        ],
+        semantic(
+            &Dmp::default(),
+            "明日会議があります。",
+            "明日会社で会議があります。"
+        )
+    );
+    assert_eq!(
+        vec![
+            Diff::Keep("明日".to_string()),
+            Diff::Add("会社で".to_string()),
+            Diff::Keep("会議があります。".to_string())
+        ],
+        semantic(&dmp, "明日会議があります。", "明日会社で会議があります。")
+    );
+    assert_eq!(
+        vec![
+            Diff::Keep("私は".to_string()),
+            Diff::Add("日本の歴史と".to_string()),
+            Diff::Keep("日本語を勉強しています。".to_string())
+        ],
+        semantic(
+            &dmp,
+            "私は日本語を勉強しています。",
+            "私は日本の歴史と日本語を勉強しています。"
+        )
+    );
+}
+
+/// Without the dictionary feature runs of Chinese, Thai or one Japanese
+/// script have no word boundaries inside them.
+#[cfg(not(feature = "dictionary"))]
+#[test]
+pub fn test_diff_cleanup_unicode_scoring_no_dictionary() {
+    let dmp = Dmp {
+        boundary_scoring: BoundaryScoring::Unicode,
+        ..Dmp::default()
+    };
+    let chars = |text: &str| -> Vec<char> { text.chars().collect() };
+    let score = |one: &str, two: &str| BoundaryScoring::Unicode.score(&chars(one), &chars(two));
+    // Chinese words.
+    assert_eq!(0, score("我们学习", "中文"));
+    assert_eq!(0, score("我们学", "习中文"));
+    // Thai words.
+    assert_eq!(0, score("ภาษา", "ไทย"));
+    assert_eq!(0, score("ภา", "ษาไทย"));
+    // Japanese words between Han, or before kana other than particles.
+    assert_eq!(0, score("日本", "語"));
+    assert_eq!(0, score("勉強", "して"));
+    assert_eq!(1, score("日本語", "を"));
+    assert_eq!(1, score("して", "勉強"));
+
+    // So the edits stay where the diff put them.
+    let mut diffs = dmp.diff_main("我们学习中文。", "我们学习中国历史和中文。", false);
+    dmp.diff_cleanup_semantic(&mut diffs);
+    assert_eq!(
+        vec![
+            Diff::Keep("我们学习中".to_string()),
+            Diff::Add("国历史和中".to_string()),
+            Diff::Keep("文。".to_string())
+        ],
+        diffs
+    );
+    assert_eq!(
+        vec![
+            Diff::Keep("我们".to_string()),
+            Diff::Delete("今".to_string()),
+            Diff::Add("明".to_string()),
+            Diff::Keep("天去北京。".to_string())
+        ],
+        dmp.diff_words("我们今天去北京。", "我们明天去北京。")
+    );
+}
+
+#[test]
+pub fn test_diff_words() {
+    let dmp = Dmp::default();
+    assert_eq!(
+        vec![
+            Diff::Keep("The ".to_string()),
+            Diff::Delete("quick".to_string()),
+            Diff::Add("slow".to_string()),
+            Diff::Keep(" fox.".to_string())
+        ],
+        dmp.diff_words("The quick fox.", "The slow fox.")
+    );
+    // Combining marks stay with their word.
+    assert_eq!(
+        vec![
+            Diff::Delete("cafe\u{301}".to_string()),
+            Diff::Add("cafe\u{301}s".to_string()),
+            Diff::Keep(" ok".to_string())
+        ],
+        dmp.diff_words("cafe\u{301} ok", "cafe\u{301}s ok")
+    );
+    // Katakana runs are words.
+    assert_eq!(
+        vec![
+            Diff::Keep("東京".to_string()),
+            Diff::Delete("タワー".to_string()),
+            Diff::Add("スカイツリー".to_string()),
+            Diff::Keep("へ".to_string())
+        ],
+        dmp.diff_words("東京タワーへ", "東京スカイツリーへ")
+    );
+
+    // Equal words spelled differently are replaced.
+    let dmp = Dmp {
+        ignore_case: true,
+        ..Dmp::default()
+    };
+    assert_eq!(
+        vec![
+            Diff::Delete("Hello".to_string()),
+            Diff::Add("hello".to_string()),
+            Diff::Keep(" ".to_string()),
+            Diff::Delete("world".to_string()),
+            Diff::Add("there".to_string())
+        ],
+        dmp.diff_words("Hello world", "hello there")
+    );
+}
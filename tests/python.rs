@@ -0,0 +1,74 @@
+//! Runs tests/python/test_drop_in.py against the Python module.  Needs
+//! `--features python` and a python3 with a shared libpython; the crate is
+//! an rlib unless asked otherwise, so the module is built here first with
+//! `cargo rustc`.
+#![cfg(feature = "python")]
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[test]
+pub fn test_drop_in() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    // Build in a target directory of its own, so the cdylib of the ffi
+    // tests is not rebuilt with other features every time.
+    let target_dir: PathBuf = env::current_exe()
+        .unwrap()
+        .ancestors()
+        .nth(3)
+        .unwrap()
+        .join("python");
+
+    let mut cargo = Command::new(env!("CARGO"));
+    cargo
+        .args([
+            "rustc",
+            "--lib",
+            "--crate-type",
+            "cdylib",
+            "--features",
+            "python",
+        ])
+        .arg("--manifest-path")
+        .arg(root.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target_dir);
+    let profile = if cfg!(debug_assertions) {
+        "debug"
+    } else {
+        cargo.arg("--release");
+        "release"
+    };
+    let status = cargo.status().expect("failed to run cargo");
+    assert!(status.success(), "building the Python module failed");
+
+    // Python imports the module by its file name.
+    let lib_dir = target_dir.join(profile);
+    let (built, module) = if cfg!(windows) {
+        ("rusty_diff_match_patch.dll", "rusty_diff_match_patch.pyd")
+    } else if cfg!(target_os = "macos") {
+        (
+            "librusty_diff_match_patch.dylib",
+            "rusty_diff_match_patch.so",
+        )
+    } else {
+        ("librusty_diff_match_patch.so", "rusty_diff_match_patch.so")
+    };
+    let module_dir = target_dir.join("module");
+    fs::create_dir_all(&module_dir).unwrap();
+    fs::copy(lib_dir.join(built), module_dir.join(module)).unwrap();
+
+    let python = env::var("PYTHON").unwrap_or_else(|_| "python3".to_string());
+    let output = Command::new(python)
+        .arg(root.join("tests/python/test_drop_in.py"))
+        .env("PYTHONPATH", &module_dir)
+        .output()
+        .expect("failed to run python3");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,140 @@
+#!/usr/bin/env python3
+"""Check the Python bindings against the API of the diff-match-patch package.
+
+    maturin develop
+    python3 tests/python/test_drop_in.py
+
+Expected values are those of the Python port's own test suite.
+"""
+
+import time
+import unittest
+
+from rusty_diff_match_patch import diff_match_patch, patch_obj
+
+
+class DropInTest(unittest.TestCase):
+    def setUp(self):
+        self.dmp = diff_match_patch()
+
+    def test_attributes(self):
+        self.assertEqual(1.0, self.dmp.Diff_Timeout)
+        self.assertEqual(4, self.dmp.Diff_EditCost)
+        self.assertEqual(32, self.dmp.Match_MaxBits)
+        self.dmp.Diff_Timeout = 0
+        self.assertEqual(0, self.dmp.Diff_Timeout)
+        self.dmp.Match_Threshold = 0.7
+        self.assertAlmostEqual(0.7, self.dmp.Match_Threshold, places=6)
+
+    def test_diff_main(self):
+        self.assertEqual([], self.dmp.diff_main("", ""))
+        self.assertEqual(
+            [(self.dmp.DIFF_DELETE, "a"), (self.dmp.DIFF_INSERT, "b")],
+            self.dmp.diff_main("a", "b", False),
+        )
+        self.assertEqual(
+            [(0, "ab"), (1, "123"), (0, "c")],
+            self.dmp.diff_main("abc", "ab123c", False),
+        )
+        self.assertEqual(
+            [(-1, "a"), (1, "ڀ"), (0, "x"), (-1, "\t"), (1, "\x00")],
+            self.dmp.diff_main("ax\t", "ڀx\x00", False),
+        )
+
+    def test_diff_main_deadline(self):
+        # A deadline which has passed gives up at once, even without a
+        # Diff_Timeout.
+        self.dmp.Diff_Timeout = 0
+        self.assertEqual(
+            [(-1, "cat"), (1, "map")],
+            self.dmp.diff_main("cat", "map", False, time.time() - 1),
+        )
+        self.assertEqual(
+            [(-1, "c"), (1, "m"), (0, "a"), (-1, "t"), (1, "p")],
+            self.dmp.diff_main("cat", "map", False, time.time() + 60),
+        )
+
+    def test_cleanup_in_place(self):
+        diffs = [(-1, "abc"), (1, "ABC"), (0, "1234"), (-1, "wxyz")]
+        self.dmp.diff_cleanupSemantic(diffs)
+        self.assertEqual([(-1, "abc"), (1, "ABC"), (0, "1234"), (-1, "wxyz")], diffs)
+        diffs = [(-1, "a"), (0, "b"), (-1, "c")]
+        self.dmp.diff_cleanupSemantic(diffs)
+        self.assertEqual([(-1, "abc"), (1, "b")], diffs)
+
+    def test_diff_texts(self):
+        diffs = [(0, "jump"), (-1, "s"), (1, "ed"), (0, " over "), (-1, "the"), (1, "a"), (0, " lazy")]
+        self.assertEqual("jumps over the lazy", self.dmp.diff_text1(diffs))
+        self.assertEqual("jumped over a lazy", self.dmp.diff_text2(diffs))
+        self.assertEqual(4, self.dmp.diff_levenshtein([(-1, "abc"), (1, "1234"), (0, "xyz")]))
+        self.assertEqual(
+            '<span>a&para;<br></span><del style="background:#ffe6e6;">&lt;B&gt;b&lt;/B&gt;</del>'
+            '<ins style="background:#e6ffe6;">c&amp;d</ins>',
+            self.dmp.diff_prettyHtml([(0, "a\n"), (-1, "<B>b</B>"), (1, "c&d")]),
+        )
+
+    def test_delta(self):
+        diffs = self.dmp.diff_main("jumps over the lazy", "jumped over a lazy old dog")
+        delta = self.dmp.diff_toDelta(diffs)
+        self.assertEqual(diffs, self.dmp.diff_fromDelta("jumps over the lazy", delta))
+        with self.assertRaises(ValueError):
+            self.dmp.diff_fromDelta("abc", "=4")
+
+    def test_match_main(self):
+        self.assertEqual(3, self.dmp.match_main("abcdef", "defy", 4))
+        self.dmp.Match_Threshold = 0.7
+        self.assertEqual(
+            4,
+            self.dmp.match_main("I am the very model of a modern major general.", " that berry ", 5),
+        )
+
+    def test_patches(self):
+        text1 = "The quick brown fox jumps over the lazy dog."
+        text2 = "That quick brown fox jumped over a lazy dog."
+        expected = (
+            "@@ -1,8 +1,7 @@\n Th\n-at\n+e\n  qui\n"
+            "@@ -21,17 +21,18 @@\n jump\n-ed\n+s\n  over \n-a\n+the\n  laz\n"
+        )
+        patches = self.dmp.patch_make(text2, text1)
+        self.assertEqual(expected, self.dmp.patch_toText(patches))
+        diffs = self.dmp.diff_main(text1, text2, False)
+        for args in [(diffs,), (text1, diffs), (text1, text2, diffs)]:
+            self.assertEqual(
+                self.dmp.patch_toText(self.dmp.patch_make(text1, text2)),
+                self.dmp.patch_toText(self.dmp.patch_make(*args)),
+            )
+
+        patches = self.dmp.patch_fromText(self.dmp.patch_toText(self.dmp.patch_make(text1, text2)))
+        self.assertIsInstance(patches[0], patch_obj)
+        self.assertEqual("@@ -1,11 +1,12 @@\n Th\n-e\n+at\n  quick b\n", str(patches[0]))
+        self.assertEqual(
+            (text2, [True, True]),
+            self.dmp.patch_apply(patches, text1),
+        )
+        self.assertEqual(
+            ("That quick red rabbit jumped over a tired tiger.", [True, True]),
+            self.dmp.patch_apply(patches, "The quick red rabbit jumps over the tired tiger."),
+        )
+        with self.assertRaises(ValueError):
+            self.dmp.patch_fromText("Bad\nPatch\n")
+
+    def test_patch_obj(self):
+        p = patch_obj()
+        p.start1 = 20
+        p.start2 = 21
+        p.length1 = 18
+        p.length2 = 17
+        p.diffs = [(0, "jump"), (-1, "s"), (1, "ed"), (0, " over "), (-1, "the"), (1, "a"), (0, "\nlaz")]
+        self.assertEqual("@@ -21,18 +22,17 @@\n jump\n-s\n+ed\n  over \n-the\n+a\n %0Alaz\n", str(p))
+
+        # The list is the patch's own, so changing it changes the patch.
+        p = self.dmp.patch_make("abc", "abd")[0]
+        p.diffs.append((1, "e"))
+        self.assertEqual((1, "e"), p.diffs[-1])
+        p.diffs = ((0, "ab"), (-1, "c"), (1, "d"))
+        self.assertIsInstance(p.diffs, list)
+        self.assertEqual([(0, "ab"), (-1, "c"), (1, "d")], p.diffs)
+
+
+if __name__ == "__main__":
+    unittest.main()
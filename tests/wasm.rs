@@ -0,0 +1,85 @@
+//! Runs the WebAssembly bindings under Node.js with wasm-bindgen-test.
+//! Needs `--features wasm`, the wasm32-unknown-unknown target, Node.js and
+//! wasm-bindgen-test-runner, which .cargo/config.toml sets as the runner.
+//! The wasm job of .github/workflows/ci.yml installs them and runs:
+//!
+//!     cargo test --target wasm32-unknown-unknown --features wasm --test wasm
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use js_sys::Array;
+use rusty_diff_match_patch::DiffMatchPatch;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+/// The diffs of a JavaScript array as (op, text) pairs.
+fn diffs_from_js(diffs: &Array) -> Vec<(i32, String)> {
+    diffs
+        .iter()
+        .map(|diff| {
+            let diff = Array::from(&diff);
+            (
+                diff.get(0).as_f64().unwrap() as i32,
+                diff.get(1).as_string().unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[wasm_bindgen_test]
+pub fn test_diff_main() {
+    let dmp = DiffMatchPatch::new();
+    assert_eq!(
+        vec![
+            (0, "ab".to_string()),
+            (1, "123".to_string()),
+            (0, "c".to_string())
+        ],
+        diffs_from_js(&dmp.diff_main("abc", "ab123c", Some(false)))
+    );
+
+    // The array is cleaned up in place.
+    let diffs = dmp.diff_main("mouse", "sofas", Some(false));
+    dmp.diff_cleanup_semantic(&diffs).unwrap();
+    assert_eq!(
+        vec![(-1, "mouse".to_string()), (1, "sofas".to_string())],
+        diffs_from_js(&diffs)
+    );
+}
+
+#[wasm_bindgen_test]
+pub fn test_options() {
+    let mut dmp = DiffMatchPatch::new();
+    assert_eq!(1.0, dmp.diff_timeout());
+    dmp.set_diff_timeout(0.0);
+    assert_eq!(0.0, dmp.diff_timeout());
+    dmp.set_match_threshold(0.25);
+    assert_eq!(0.25, dmp.match_threshold());
+}
+
+#[wasm_bindgen_test]
+pub fn test_patch() {
+    let dmp = DiffMatchPatch::new();
+    let text1 = "🅰🅱 The quick brown fox";
+    let text2 = "🅰🅱 The quick red fox";
+    let patches = dmp
+        .patch_make(text1.into(), text2.into(), JsValue::UNDEFINED)
+        .unwrap();
+    // Offsets count UTF-16 code units: each emoji is two of them.
+    let text = dmp.patch_to_text(&patches).unwrap();
+    assert_eq!("@@ -12,13 +12,11 @@\n ick \n-brown\n+red\n  fox\n", text);
+
+    let parsed = dmp.patch_from_text(&text).unwrap();
+    assert_eq!(text, dmp.patch_to_text(&parsed).unwrap());
+    let result = dmp.patch_apply(&parsed, text1).unwrap();
+    assert_eq!(Some(text2.to_string()), result.get(0).as_string());
+    assert_eq!(Some(true), Array::from(&result.get(1)).get(0).as_bool());
+
+    assert!(dmp.patch_from_text("Bad\nPatch\n").is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_match_main() {
+    let dmp = DiffMatchPatch::new();
+    assert_eq!(4, dmp.match_main("🅰🅱abc", "abc", 0));
+    assert_eq!(-1, dmp.match_main("", "abc", 0));
+}